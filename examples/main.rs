@@ -1,19 +1,23 @@
 extern crate ruroonga_client as groonga;
-extern crate json_flex;
+#[macro_use]
+extern crate serde_derive;
 
+use groonga::builtin::column_create::{ColumnCreate, ColumnType};
 use groonga::builtin::command_query::CommandQuery;
+use groonga::builtin::load::Load;
+use groonga::builtin::table_create::{TableCreate, TableType};
 
 const GROONGA_SERVER: &'static str = "localhost";
 
 fn create_table() {
     let mut request = groonga::HTTPRequest::new();
-    let mut command = CommandQuery::new("table_create");
+    let command = TableCreate::new("Sites", TableType::HashKey)
+        .key_type("ShortText")
+        .build()
+        .unwrap();
     let uri_base = groonga::URIBase::new()
         .base_uri(GROONGA_SERVER.to_string())
         .build();
-    command.set_argument(vec![("name", "Sites"),
-                              ("flags", "TABLE_HASH_KEY"),
-                              ("key_type", "ShortText")]);
     let url = groonga::RequestURI::new(uri_base, command.encode()).url();
     println!("load url: {}", url);
     let res = request.get(url);
@@ -23,11 +27,12 @@ fn create_table() {
 
 fn create_column() {
     let mut request = groonga::HTTPRequest::new();
-    let mut command = CommandQuery::new("column_create");
+    let command = ColumnCreate::new("Sites", "title", ColumnType::Scalar, "ShortText")
+        .build()
+        .unwrap();
     let uri_base = groonga::URIBase::new()
         .base_uri(GROONGA_SERVER.to_string())
         .build();
-    command.set_argument(vec![("table", "Sites"), ("name", "title"), ("type", "ShortText")]);
     let url = groonga::RequestURI::new(uri_base, command.encode()).url();
     println!("load url: {}", url);
     let res = request.get(url);
@@ -35,36 +40,48 @@ fn create_column() {
     println!("result: {}", result);
 }
 
+#[derive(Serialize)]
+struct Site {
+    #[serde(rename = "_key")]
+    key: &'static str,
+    title: &'static str,
+}
+
 fn load() {
-    let data = r#"
-[
-{"_key":"http://example.org/","title":"This is test record 1!"},
-{"_key":"http://example.net/","title":"test record 2."},
-{"_key":"http://example.com/","title":"test test record three."},
-{"_key":"http://example.net/afr","title":"test record four."},
-{"_key":"http://example.org/aba","title":"test test test record five."},
-{"_key":"http://example.com/rab","title":"test test test test record six."},
-{"_key":"http://example.net/atv","title":"test test test record seven."},
-{"_key":"http://example.org/gat","title":"test test record eight."},
-{"_key":"http://example.com/vdw","title":"test test record nine."},
-]"#;
+    let sites = vec![("http://example.org/", "This is test record 1!"),
+                     ("http://example.net/", "test record 2."),
+                     ("http://example.com/", "test test record three."),
+                     ("http://example.net/afr", "test record four."),
+                     ("http://example.org/aba", "test test test record five."),
+                     ("http://example.com/rab", "test test test test record six."),
+                     ("http://example.net/atv", "test test test record seven."),
+                     ("http://example.org/gat", "test test record eight."),
+                     ("http://example.com/vdw", "test test record nine.")];
+    let load = Load::new("Sites")
+        .values(sites.into_iter().map(|(key, title)| Site { key: key, title: title }))
+        .unwrap()
+        .output_errors(true);
     let mut request = groonga::HTTPRequest::new();
-    let mut load_command = CommandQuery::new("load");
     let uri_base = groonga::URIBase::new()
         .base_uri(GROONGA_SERVER.to_string())
         .build();
-    load_command.set_argument(vec![("table", "Sites")]);
-    let load_url = groonga::RequestURI::new(uri_base, load_command.encode()).url();
+    let load_url = groonga::RequestURI::new(uri_base, load.command().encode()).url();
     println!("load url: {}", load_url);
-    println!("load data: {}", data);
-    let load_res = request.load(load_url, data.to_string());
+    println!("load data: {}", load.body());
+    let load_res = request.load(load_url, load.body().to_string());
     let load_result = request.receive(&mut load_res.unwrap()).unwrap();
     println!("result: {}", load_result);
+    let load_result = groonga::ResultParser::parse(&load_result).unwrap().load_result().unwrap();
+    println!("loaded: {}, errors: {:?}",
+             load_result.n_loaded_records(),
+             load_result.errors());
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize)]
 struct Result {
+    #[serde(rename = "_id")]
     id: i64,
+    #[serde(rename = "_key")]
     key: String,
     title: String,
 }
@@ -84,28 +101,21 @@ fn main() {
     let res = request.get(url);
     let result = request.receive(&mut res.unwrap()).unwrap();
     println!("result: {}", result);
-    let mut decode = groonga::ResultParser::new(result);
+    let decode = groonga::ResultParser::parse(&result).unwrap();
     println!("status: {:?}", decode.status().unwrap());
     println!("start: {:?}", decode.start_time().unwrap());
     println!("elapsed: {:?}", decode.elapsed_time().unwrap());
-    if decode.status().unwrap().clone() == 0 {
+    if decode.status().unwrap() == 0 {
         println!("matched columns: {:?}", decode.matched_columns().unwrap());
-        let decoded_vec = decode.into_row().columns().unwrap();
-        println!("index access: {:?}", decoded_vec[2]);
-        // Read got response
-        let mut result_vec: Vec<Result> = Vec::new();
+        let rows = decode.into_row().unwrap();
+        println!("total hits: {:?}", rows.total_hits());
+        println!("column definitions: {:?}", rows.column_defs());
         println!("-- Display raw decoded json values --");
-        // Skip reading result header
-        for v in decoded_vec.iter().skip(2) {
-            println!("{:?}", v);
-            let raw = v.unwrap_vec();
-            let elem = Result {
-                id: raw[0].unwrap_i64().clone(),
-                key: raw[1].unwrap_string().clone(),
-                title: raw[2].unwrap_string().clone(),
-            };
-            result_vec.push(elem.clone());
+        for record in rows.records() {
+            println!("{:?}: {:?}", record.get("_key"), record.get("title"));
         }
+        // Map records by column names
+        let result_vec: Vec<Result> = decode.deserialize().unwrap();
         println!("-- Mapped to user-defined struct -- ");
         println!("{:?}", result_vec);
     } else {
@@ -0,0 +1,351 @@
+//! `#[derive(GroongaTable)]` which implements `ruroonga_client::GroongaTable`.
+//!
+//! Each field is mapped to a column. The column name is the field name,
+//! or `rename` of `#[serde(...)]` attribute so that load and select mapping
+//! by serde agrees with the schema. `rename_all` of the struct is applied too.
+//! Fields with `skip`, `skip_serializing` or `skip_deserializing` are not
+//! columns. `_key` column becomes the key of the table and `_id` column is
+//! not created.
+//!
+//! Attributes of struct:
+//!
+//! * `#[groonga(table = "Sites")]`: table name. Default is the struct name.
+//! * `#[groonga(table_type = "pat_key")]`: one of `hash_key`, `pat_key`, `dat_key`
+//!   and `no_key`. Default is `hash_key` with `_key` column, otherwise `no_key`.
+//! * `#[groonga(key_type = "ShortText")]`: overrides type of `_key` column.
+//! * `#[groonga(normalizer = "NormalizerAuto")]`
+//! * `#[groonga(tokenizer = "TokenBigram")]`
+//! * `#[groonga(token_filters = "TokenFilterStopWord")]`: comma separated filters.
+//!
+//! Attributes of field:
+//!
+//! * `#[groonga(value_type = "Text")]`: overrides type of column.
+//! * `#[groonga(index = "Terms")]`: creates index column of this column in
+//!   lexicon `Terms`, whose name is `<table>_<column>` in lower case.
+//! * `#[groonga(with_position)]`: sets `WITH_POSITION` flag to the index column.
+//!
+//! # Examples
+//!
+//! ```
+//! extern crate ruroonga_client;
+//! #[macro_use]
+//! extern crate ruroonga_client_derive;
+//! #[macro_use]
+//! extern crate serde_derive;
+//!
+//! use ruroonga_client::GroongaTable;
+//!
+//! #[derive(Serialize, Deserialize, GroongaTable)]
+//! #[groonga(table = "Sites")]
+//! struct Site {
+//!     #[serde(rename = "_key")]
+//!     key: String,
+//!     #[groonga(index = "Terms", with_position)]
+//!     title: String,
+//!     tags: Vec<String>,
+//! }
+//!
+//! # fn main() {
+//! assert_eq!("/d/table_create?name=Sites&flags=TABLE_HASH_KEY&key_type=ShortText",
+//!            Site::table_create().build().unwrap().encode());
+//! # }
+//! ```
+
+extern crate proc_macro;
+extern crate syn;
+#[macro_use]
+extern crate quote;
+
+use proc_macro::TokenStream;
+use syn::{Body, DeriveInput, Ident, Lit, MetaItem, NestedMetaItem, Ty, VariantData};
+
+#[proc_macro_derive(GroongaTable, attributes(groonga))]
+pub fn derive_groonga_table(input: TokenStream) -> TokenStream {
+    let input = syn::parse_derive_input(&input.to_string()).unwrap();
+    let expanded = expand(&input);
+    expanded.parse().unwrap()
+}
+
+// Options which are specified by `#[groonga(...)]`.
+#[derive(Default)]
+struct Options {
+    values: Vec<(String, String)>,
+    words: Vec<String>,
+}
+
+impl Options {
+    fn parse(attrs: &[syn::Attribute]) -> Options {
+        let mut options = Options::default();
+        for items in attrs.iter().filter_map(|attr| nested_items(&attr.value, "groonga")) {
+            for item in items {
+                match *item {
+                    NestedMetaItem::MetaItem(MetaItem::NameValue(ref name,
+                                                                 Lit::Str(ref value, _))) => {
+                        options.values.push((name.to_string(), value.clone()));
+                    }
+                    NestedMetaItem::MetaItem(MetaItem::Word(ref name)) => {
+                        options.words.push(name.to_string());
+                    }
+                    _ => panic!("#[derive(GroongaTable)]: unsupported attribute {:?}", item),
+                }
+            }
+        }
+        options
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|pair| pair.0 == name)
+            .map(|pair| pair.1.as_str())
+    }
+
+    fn has_word(&self, name: &str) -> bool {
+        self.words.iter().any(|word| word == name)
+    }
+}
+
+fn nested_items<'a>(item: &'a MetaItem, name: &str) -> Option<&'a [NestedMetaItem]> {
+    match *item {
+        MetaItem::List(ref ident, ref items) if ident == name => Some(items),
+        _ => None,
+    }
+}
+
+// Items of `#[serde(...)]` attributes.
+fn serde_items(attrs: &[syn::Attribute]) -> Vec<&NestedMetaItem> {
+    attrs.iter()
+        .filter_map(|attr| nested_items(&attr.value, "serde"))
+        .flat_map(|items| items.iter())
+        .collect()
+}
+
+fn serde_value<'a>(attrs: &'a [syn::Attribute], name: &str) -> Option<&'a str> {
+    serde_items(attrs)
+        .into_iter()
+        .filter_map(|item| match *item {
+            NestedMetaItem::MetaItem(MetaItem::NameValue(ref key, Lit::Str(ref value, _)))
+                if key == name => Some(value.as_str()),
+            _ => None,
+        })
+        .next()
+}
+
+// A field which is skipped in either direction does not round-trip,
+// so it is not a column.
+fn is_skipped(field: &syn::Field) -> bool {
+    serde_items(&field.attrs).into_iter().any(|item| match *item {
+        NestedMetaItem::MetaItem(MetaItem::Word(ref name)) => {
+            name == "skip" || name == "skip_serializing" || name == "skip_deserializing"
+        }
+        _ => false,
+    })
+}
+
+// `#[serde(rename = "...")]` or `rename_all` of the struct decides the column name.
+fn column_name(field: &syn::Field, rename_all: Option<&str>) -> String {
+    if let Some(rename) = serde_value(&field.attrs, "rename") {
+        return rename.to_owned();
+    }
+    let name = field.ident.as_ref().unwrap().to_string();
+    let pascal_case = || {
+        name.split('_')
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => String::new(),
+                }
+            })
+            .collect::<String>()
+    };
+    match rename_all {
+        None | Some("snake_case") => name.clone(),
+        Some("lowercase") => name.to_lowercase(),
+        Some("UPPERCASE") |
+        Some("SCREAMING_SNAKE_CASE") => name.to_uppercase(),
+        Some("PascalCase") => pascal_case(),
+        Some("camelCase") => {
+            let pascal_case = pascal_case();
+            let mut chars = pascal_case.chars();
+            match chars.next() {
+                Some(first) => first.to_lowercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }
+        Some("kebab-case") => name.replace('_', "-"),
+        Some("SCREAMING-KEBAB-CASE") => name.to_uppercase().replace('_', "-"),
+        Some(rule) => panic!("#[derive(GroongaTable)]: unknown rename_all rule `{}`", rule),
+    }
+}
+
+// Return Groonga type of `ty` and whether it is a vector.
+fn groonga_type(ty: &Ty) -> Option<(&'static str, bool)> {
+    let segment = match *ty {
+        Ty::Rptr(_, ref ty) => return groonga_type(&ty.ty),
+        Ty::Path(None, ref path) => path.segments.last().unwrap(),
+        _ => return None,
+    };
+    let parameter = match segment.parameters {
+        syn::PathParameters::AngleBracketed(ref data) => data.types.first(),
+        _ => None,
+    };
+    let groonga_type = match segment.ident.as_ref() {
+        "Option" | "Box" => return parameter.and_then(groonga_type),
+        "Vec" => {
+            return parameter.and_then(groonga_type)
+                .and_then(|(groonga_type, is_vector)| if is_vector {
+                    None
+                } else {
+                    Some((groonga_type, true))
+                })
+        }
+        "bool" => "Bool",
+        "i8" => "Int8",
+        "i16" => "Int16",
+        "i32" => "Int32",
+        "i64" => "Int64",
+        "u8" => "UInt8",
+        "u16" => "UInt16",
+        "u32" => "UInt32",
+        "u64" => "UInt64",
+        "f32" | "f64" => "Float",
+        "String" | "str" | "Cow" => "ShortText",
+        _ => return None,
+    };
+    Some((groonga_type, false))
+}
+
+fn expand(input: &DeriveInput) -> quote::Tokens {
+    let name = &input.ident;
+    if !input.generics.lifetimes.is_empty() || !input.generics.ty_params.is_empty() {
+        panic!("#[derive(GroongaTable)]: generic struct is not supported");
+    }
+    let fields = match input.body {
+        Body::Struct(VariantData::Struct(ref fields)) => fields,
+        _ => panic!("#[derive(GroongaTable)]: only struct with named fields is supported"),
+    };
+    let options = Options::parse(&input.attrs);
+    let rename_all = serde_value(&input.attrs, "rename_all");
+    let table = options.value("table")
+        .map_or_else(|| name.to_string(), |table| table.to_owned());
+    let table = table.as_str();
+
+    let mut column_names = vec![];
+    let mut key_type = options.value("key_type").map(|key_type| key_type.to_owned());
+    let mut columns = vec![];
+    let mut indexes = vec![];
+    for field in fields.iter().filter(|field| !is_skipped(field)) {
+        let column = column_name(field, rename_all);
+        let field_options = Options::parse(&field.attrs);
+        let (value_type, is_vector) = match field_options.value("value_type") {
+            Some(value_type) => {
+                let is_vector = groonga_type(&field.ty)
+                    .map(|(_, is_vector)| is_vector) == Some(true);
+                (value_type.to_owned(), is_vector)
+            }
+            None => {
+                match groonga_type(&field.ty) {
+                    Some((value_type, is_vector)) => (value_type.to_owned(), is_vector),
+                    None => {
+                        panic!("#[derive(GroongaTable)]: specify \
+                                #[groonga(value_type = \"...\")] for column `{}`",
+                               column)
+                    }
+                }
+            }
+        };
+        if let Some(lexicon) = field_options.value("index") {
+            let index = format!("{}_{}", table, column).to_lowercase();
+            let with_position = field_options.has_word("with_position");
+            indexes.push(quote! {
+                ::ruroonga_client::builtin::column_create::ColumnCreate::new(
+                    #lexicon,
+                    #index,
+                    ::ruroonga_client::builtin::column_create::ColumnType::Index,
+                    #table)
+                    .with_position(#with_position)
+                    .source(#column)
+            });
+        }
+        match column.as_str() {
+            "_id" => (),
+            "_key" => {
+                if is_vector {
+                    panic!("#[derive(GroongaTable)]: _key cannot be vector");
+                }
+                if key_type.is_none() {
+                    key_type = Some(value_type);
+                }
+            }
+            _ => {
+                let column_type = Ident::new(if is_vector { "Vector" } else { "Scalar" });
+                let value_type = value_type.as_str();
+                columns.push(quote! {
+                    ::ruroonga_client::builtin::column_create::ColumnCreate::new(
+                        #table,
+                        #column,
+                        ::ruroonga_client::builtin::column_create::ColumnType::#column_type,
+                        #value_type)
+                });
+            }
+        }
+        column_names.push(column);
+    }
+
+    let table_type = match options.value("table_type") {
+        Some("hash_key") => "HashKey",
+        Some("pat_key") => "PatKey",
+        Some("dat_key") => "DatKey",
+        Some("no_key") => "NoKey",
+        Some(table_type) => {
+            panic!("#[derive(GroongaTable)]: unknown table_type `{}`", table_type)
+        }
+        None if column_names.iter().any(|column| column == "_key") => "HashKey",
+        None => "NoKey",
+    };
+    let table_type = Ident::new(table_type);
+    let mut table_create = quote! {
+        ::ruroonga_client::builtin::table_create::TableCreate::new(
+            #table,
+            ::ruroonga_client::builtin::table_create::TableType::#table_type)
+    };
+    if let Some(key_type) = key_type {
+        let key_type = key_type.as_str();
+        table_create = quote! { #table_create.key_type(#key_type) };
+    }
+    if let Some(normalizer) = options.value("normalizer") {
+        table_create = quote! { #table_create.normalizer(#normalizer) };
+    }
+    if let Some(tokenizer) = options.value("tokenizer") {
+        table_create = quote! { #table_create.default_tokenizer(#tokenizer) };
+    }
+    if let Some(token_filters) = options.value("token_filters") {
+        let token_filters = token_filters.split(',').map(|filter| filter.trim());
+        table_create = quote! { #table_create.token_filters(vec![#(#token_filters),*]) };
+    }
+    columns.extend(indexes);
+    let column_names = column_names.iter().map(|column| column.as_str());
+
+    quote! {
+        impl ::ruroonga_client::GroongaTable for #name {
+            fn table_name() -> &'static str {
+                #table
+            }
+
+            fn table_create() -> ::ruroonga_client::builtin::table_create::TableCreate<'static> {
+                #table_create
+            }
+
+            fn column_creates()
+                -> Vec<::ruroonga_client::builtin::column_create::ColumnCreate<'static>>
+            {
+                vec![#(#columns),*]
+            }
+
+            fn column_names() -> Vec<&'static str> {
+                vec![#(#column_names),*]
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+extern crate ruroonga_client;
+#[macro_use]
+extern crate ruroonga_client_derive;
+#[macro_use]
+extern crate serde_derive;
+
+use ruroonga_client::{GroongaTable, LiveSchema, ResultParser, Schema, SchemaChange};
+use ruroonga_client::builtin::table_create::{TableCreate, TableType};
+
+#[derive(Serialize, Deserialize, GroongaTable, Debug, PartialEq)]
+#[groonga(table = "Sites", table_type = "pat_key", normalizer = "NormalizerAuto")]
+struct Site {
+    #[serde(rename = "_key")]
+    key: String,
+    #[groonga(value_type = "Text", index = "Terms", with_position)]
+    title: String,
+    n_likes: Option<u32>,
+    tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, GroongaTable)]
+struct Logs {
+    #[serde(rename = "_id")]
+    id: u32,
+    message: String,
+}
+
+#[allow(dead_code)]
+#[derive(Serialize, Deserialize, GroongaTable)]
+#[serde(rename_all = "camelCase")]
+struct Users {
+    #[serde(rename = "_key")]
+    user_name: String,
+    #[groonga(index = "Terms")]
+    display_name: String,
+    n_followers: u32,
+    #[serde(skip)]
+    cached_rank: u32,
+    #[serde(skip_serializing)]
+    password_hash: String,
+    #[serde(skip_deserializing)]
+    session_token: String,
+}
+
+#[test]
+fn declare_table_and_columns() {
+    assert_eq!("Sites", Site::table_name());
+    assert_eq!("name=Sites&flags=TABLE_PAT_KEY&key_type=ShortText&normalizer=NormalizerAuto",
+               Site::table_create().build().unwrap().make_query());
+    let columns = Site::column_creates()
+        .into_iter()
+        .map(|column| column.build().unwrap().make_query())
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["table=Sites&name=title&flags=COLUMN_SCALAR&type=Text",
+                    "table=Sites&name=n_likes&flags=COLUMN_SCALAR&type=UInt32",
+                    "table=Sites&name=tags&flags=COLUMN_VECTOR&type=ShortText",
+                    "table=Terms&name=sites_title&flags=COLUMN_INDEX%7CWITH_POSITION&\
+                     type=Sites&source=title"],
+               columns);
+}
+
+#[test]
+fn declare_table_without_key() {
+    assert_eq!("name=Logs&flags=TABLE_NO_KEY",
+               Logs::table_create().build().unwrap().make_query());
+    assert_eq!(vec!["_id", "message"], Logs::column_names());
+    assert_eq!(1, Logs::column_creates().len());
+}
+
+#[test]
+fn apply_serde_rename_all() {
+    assert_eq!("name=Users&flags=TABLE_HASH_KEY&key_type=ShortText",
+               Users::table_create().build().unwrap().make_query());
+    let columns = Users::column_creates()
+        .into_iter()
+        .map(|column| column.build().unwrap().make_query())
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["table=Users&name=displayName&flags=COLUMN_SCALAR&type=ShortText",
+                    "table=Users&name=nFollowers&flags=COLUMN_SCALAR&type=UInt32",
+                    "table=Terms&name=users_displayname&flags=COLUMN_INDEX&\
+                     type=Users&source=displayName"],
+               columns);
+}
+
+#[test]
+fn exclude_skipped_fields() {
+    assert_eq!(vec!["_key", "displayName", "nFollowers"], Users::column_names());
+}
+
+#[test]
+fn plan_schema_of_derived_tables() {
+    let live = LiveSchema::from_body(&"{\"tables\":{}}".parse().unwrap()).unwrap();
+    let migration = Schema::new()
+        .table(TableCreate::new("Terms", TableType::PatKey)
+            .key_type("ShortText")
+            .default_tokenizer("TokenBigram"))
+        .declare::<Site>()
+        .declare::<Logs>()
+        .diff(&live)
+        .unwrap();
+    assert_eq!(SchemaChange::MissingIndex("Terms.sites_title".to_owned()),
+               migration.changes()[6]);
+    assert_eq!(8, migration.commands().len());
+}
+
+#[test]
+fn load_and_select_records() {
+    let site = Site {
+        key: "http://example.org/".to_owned(),
+        title: "test record".to_owned(),
+        n_likes: None,
+        tags: vec!["groonga".to_owned()],
+    };
+    let load = Site::load(vec![site]).unwrap();
+    assert_eq!("/d/load?table=Sites", load.command().encode());
+    assert_eq!("/d/select?table=Sites&output_columns=_key%2Ctitle%2Cn_likes%2Ctags",
+               Site::select().build().encode());
+
+    let result = ResultParser::parse(r#"[[0,0.0,0.0],
+        [[[1],[["_key","ShortText"],["title","Text"],["n_likes","UInt32"],["tags","ShortText"]],
+          ["http://example.org/","test record",0,["groonga"]]]]]"#)
+        .unwrap();
+    let sites: Vec<Site> = result.deserialize().unwrap();
+    assert_eq!(Site {
+                   key: "http://example.org/".to_owned(),
+                   title: "test record".to_owned(),
+                   n_likes: Some(0),
+                   tags: vec!["groonga".to_owned()],
+               },
+               sites[0]);
+}
@@ -0,0 +1,256 @@
+use std::borrow::Cow;
+use std::fmt;
+use builtin::command_query::CommandQuery;
+use error::Error;
+
+/// Type of column, a.k.a. `COLUMN_*` flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColumnType {
+    /// `COLUMN_SCALAR`
+    Scalar,
+    /// `COLUMN_VECTOR`
+    Vector,
+    /// `COLUMN_INDEX`
+    Index,
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let column_type = match *self {
+            ColumnType::Scalar => "COLUMN_SCALAR",
+            ColumnType::Vector => "COLUMN_VECTOR",
+            ColumnType::Index => "COLUMN_INDEX",
+        };
+        write!(f, "{}", column_type)
+    }
+}
+
+/// Compression of column value, a.k.a. `COMPRESS_*` flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// `COMPRESS_ZLIB`
+    Zlib,
+    /// `COMPRESS_LZ4`
+    Lz4,
+    /// `COMPRESS_ZSTD`
+    Zstd,
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let compression = match *self {
+            Compression::Zlib => "COMPRESS_ZLIB",
+            Compression::Lz4 => "COMPRESS_LZ4",
+            Compression::Zstd => "COMPRESS_ZSTD",
+        };
+        write!(f, "{}", compression)
+    }
+}
+
+/// Typed builder of `column_create` command.
+///
+/// # Examples
+///
+/// ```
+/// use ruroonga_client::builtin::column_create::{ColumnCreate, ColumnType};
+///
+/// let command = ColumnCreate::new("Terms", "sites_title", ColumnType::Index, "Sites")
+///     .with_position(true)
+///     .source("title")
+///     .build()
+///     .unwrap();
+/// assert_eq!("/d/column_create?table=Terms&name=sites_title&\
+///             flags=COLUMN_INDEX%7CWITH_POSITION&type=Sites&source=title",
+///            command.encode());
+/// ```
+#[derive(Clone, Debug)]
+pub struct ColumnCreate<'a> {
+    table: Cow<'a, str>,
+    name: Cow<'a, str>,
+    column_type: ColumnType,
+    value_type: Cow<'a, str>,
+    with_section: bool,
+    with_weight: bool,
+    with_position: bool,
+    compression: Option<Compression>,
+    sources: Vec<Cow<'a, str>>,
+}
+
+impl<'a> ColumnCreate<'a> {
+    /// Create `column_create` command builder.
+    ///
+    /// `value_type` is a type or a table name, which is a lexicon
+    /// for `ColumnType::Index`.
+    pub fn new<T, N, V>(table: T,
+                        name: N,
+                        column_type: ColumnType,
+                        value_type: V)
+                        -> ColumnCreate<'a>
+        where T: Into<Cow<'a, str>>,
+              N: Into<Cow<'a, str>>,
+              V: Into<Cow<'a, str>>
+    {
+        ColumnCreate {
+            table: table.into(),
+            name: name.into(),
+            column_type: column_type,
+            value_type: value_type.into(),
+            with_section: false,
+            with_weight: false,
+            with_position: false,
+            compression: None,
+            sources: vec![],
+        }
+    }
+
+    /// Set `WITH_SECTION` flag. It is only for index column.
+    pub fn with_section(mut self, with_section: bool) -> ColumnCreate<'a> {
+        self.with_section = with_section;
+        self
+    }
+
+    /// Set `WITH_WEIGHT` flag. It is only for vector and index column.
+    pub fn with_weight(mut self, with_weight: bool) -> ColumnCreate<'a> {
+        self.with_weight = with_weight;
+        self
+    }
+
+    /// Set `WITH_POSITION` flag. It is only for index column.
+    pub fn with_position(mut self, with_position: bool) -> ColumnCreate<'a> {
+        self.with_position = with_position;
+        self
+    }
+
+    /// Set compression. It is only for scalar and vector column.
+    pub fn compress(mut self, compression: Compression) -> ColumnCreate<'a> {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Append source column of index column.
+    pub fn source<T>(mut self, source: T) -> ColumnCreate<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.sources.push(source.into());
+        self
+    }
+
+    // Reject combinations which Groonga does not accept.
+    fn validate(&self) -> Result<(), Error> {
+        let invalid = |message: &str| {
+            Err(Error::InvalidArgument(format!("{}.{}: {} {}",
+                                               self.table,
+                                               self.name,
+                                               self.column_type,
+                                               message)))
+        };
+        let is_index = self.column_type == ColumnType::Index;
+        if !is_index && (self.with_section || self.with_position) {
+            return invalid("cannot have WITH_SECTION nor WITH_POSITION");
+        }
+        if self.column_type == ColumnType::Scalar && self.with_weight {
+            return invalid("cannot have WITH_WEIGHT");
+        }
+        if is_index && self.compression.is_some() {
+            return invalid("cannot be compressed");
+        }
+        if !is_index && !self.sources.is_empty() {
+            return invalid("cannot have source");
+        }
+        if is_index && self.sources.len() > 1 && !self.with_section {
+            return invalid("requires WITH_SECTION for multiple sources");
+        }
+        Ok(())
+    }
+
+    /// Build `column_create` command query.
+    ///
+    /// This method returns `Error::InvalidArgument` for invalid combination
+    /// of column type, flags and sources.
+    pub fn build(self) -> Result<CommandQuery<'a>, Error> {
+        try!(self.validate());
+        let mut flags = vec![self.column_type.to_string()];
+        if self.with_section {
+            flags.push("WITH_SECTION".to_owned());
+        }
+        if self.with_weight {
+            flags.push("WITH_WEIGHT".to_owned());
+        }
+        if self.with_position {
+            flags.push("WITH_POSITION".to_owned());
+        }
+        if let Some(compression) = self.compression {
+            flags.push(compression.to_string());
+        }
+        let mut command = CommandQuery::new("column_create");
+        command.push_argument("table", self.table);
+        command.push_argument("name", self.name);
+        command.push_argument("flags", flags.join("|"));
+        command.push_argument("type", self.value_type);
+        if !self.sources.is_empty() {
+            command.push_argument("source", self.sources.join(","));
+        }
+        Ok(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn construct_scalar_column() {
+        let command = ColumnCreate::new("Sites", "title", ColumnType::Scalar, "ShortText")
+            .build()
+            .unwrap();
+        assert_eq!("/d/column_create?table=Sites&name=title&flags=COLUMN_SCALAR&type=ShortText",
+                   command.encode());
+    }
+
+    #[test]
+    fn construct_compressed_vector_column() {
+        let command = ColumnCreate::new("Sites", "tags", ColumnType::Vector, "ShortText")
+            .with_weight(true)
+            .compress(Compression::Zstd)
+            .build()
+            .unwrap();
+        assert_eq!("table=Sites&name=tags&flags=COLUMN_VECTOR%7CWITH_WEIGHT%7CCOMPRESS_ZSTD&\
+                    type=ShortText",
+                   command.make_query());
+    }
+
+    #[test]
+    fn construct_multiple_column_index() {
+        let command = ColumnCreate::new("Terms", "sites", ColumnType::Index, "Sites")
+            .with_section(true)
+            .with_position(true)
+            .source("title")
+            .source("body")
+            .build()
+            .unwrap();
+        assert_eq!("table=Terms&name=sites&flags=COLUMN_INDEX%7CWITH_SECTION%7CWITH_POSITION&\
+                    type=Sites&source=title%2Cbody",
+                   command.make_query());
+    }
+
+    #[test]
+    fn reject_invalid_combinations() {
+        let invalid = vec![ColumnCreate::new("Sites", "title", ColumnType::Scalar, "ShortText")
+                               .with_position(true),
+                           ColumnCreate::new("Sites", "title", ColumnType::Scalar, "ShortText")
+                               .with_weight(true),
+                           ColumnCreate::new("Sites", "tags", ColumnType::Vector, "ShortText")
+                               .source("title"),
+                           ColumnCreate::new("Terms", "sites", ColumnType::Index, "Sites")
+                               .compress(Compression::Lz4),
+                           ColumnCreate::new("Terms", "sites", ColumnType::Index, "Sites")
+                               .source("title")
+                               .source("body")];
+        for column in invalid {
+            match column.build() {
+                Err(Error::InvalidArgument(_)) => (),
+                _ => panic!("invalid combination should be rejected"),
+            }
+        }
+    }
+}
@@ -1,12 +1,14 @@
 use url::form_urlencoded;
 use std::borrow::Cow;
+use error::Error;
 
 pub type Query<'a> = Vec<(&'a str, &'a str)>;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct CommandQuery<'a> {
     command: Cow<'a, str>,
-    arguments: Query<'a>,
+    output_type: Option<Cow<'a, str>>,
+    arguments: Vec<(Cow<'a, str>, Cow<'a, str>)>,
     prefix: Cow<'a, str>,
 }
 
@@ -14,6 +16,7 @@ impl<'a> Default for CommandQuery<'a> {
     fn default() -> CommandQuery<'a> {
         CommandQuery {
             command: "".into(),
+            output_type: None,
             arguments: vec![],
             prefix: "/d".into(),
         }
@@ -31,19 +34,51 @@ impl<'a> CommandQuery<'a> {
     }
 
     /// Get vectorize `("key", "value")` pairs to construct url encoded query.
-    pub fn get_command(&'a self) -> Cow<'a, str> {
+    pub fn get_command(&self) -> Cow<str> {
         Cow::Borrowed(&self.command)
     }
 
+    /// Get output type, such as `json`.
+    pub fn get_output_type(&self) -> Option<&str> {
+        self.output_type.as_ref().map(|output_type| output_type.as_ref())
+    }
+
+    /// Set output type. It is encoded as `/d/select.json` or `--output_type json`.
+    pub fn set_output_type<T>(&mut self, output_type: T)
+        where T: Into<Cow<'a, str>>
+    {
+        self.output_type = Some(output_type.into())
+    }
+
     /// Set vectorize `("key", "value")` pairs to construct url encoded query.
     pub fn set_argument(&mut self, arguments: Query<'a>) {
-        self.arguments = arguments.into()
+        self.arguments = arguments.into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect()
+    }
+
+    /// Append a `("key", "value")` pair to the current arguments.
+    ///
+    /// Unlike `set_argument`, this method also accepts owned values.
+    pub fn push_argument<K, V>(&mut self, key: K, value: V)
+        where K: Into<Cow<'a, str>>,
+              V: Into<Cow<'a, str>>
+    {
+        self.arguments.push((key.into(), value.into()))
+    }
+
+    /// Get the value of the argument for `key`.
+    pub fn get_argument(&self, key: &str) -> Option<&str> {
+        self.arguments
+            .iter()
+            .find(|&&(ref name, _)| name == key)
+            .map(|&(_, ref value)| value.as_ref())
     }
 
     #[doc(hidden)]
     // get HTTP URI prefix. default: /d
     // This function is mainly provided for internal usage.
-    pub fn get_prefix(&'a self) -> Cow<'a, str> {
+    pub fn get_prefix(&self) -> Cow<str> {
         Cow::Borrowed(&self.prefix)
     }
 
@@ -59,20 +94,254 @@ impl<'a> CommandQuery<'a> {
     ///
     /// `vec![("key","value")]` interprets to `"key=value"`.
     /// And two or more value pair are concatinate with `&`.
-    pub fn make_query(&mut self) -> String {
+    pub fn make_query(&self) -> String {
         form_urlencoded::Serializer::new(String::new())
-            .extend_pairs(self.arguments.clone())
+            .extend_pairs(self.arguments.iter())
             .finish()
     }
 
     ///
     /// Create Groonga HTTP server query URL.
-    pub fn encode(&'a mut self) -> String {
-        format!("{}/{}?{}",
+    pub fn encode(&self) -> String {
+        let output_type = self.get_output_type().map_or("".to_owned(), |t| format!(".{}", t));
+        format!("{}/{}{}?{}",
                 self.get_prefix().into_owned(),
                 self.get_command().into_owned(),
+                output_type,
                 self.make_query())
     }
+
+    /// Create Groonga command line which is sent via GQTP.
+    ///
+    /// `vec![("key","value")]` interprets to `"command --key value"`.
+    /// Values are quoted with `"` when they are empty or contain spaces,
+    /// quotes, backslashes or newlines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ruroonga_client::builtin::command_query::CommandQuery;
+    ///
+    /// let mut command = CommandQuery::new("select");
+    /// command.set_argument(vec![("table", "Sites"), ("query", "title:@\"test record\"")]);
+    /// assert_eq!(r#"select --table Sites --query "title:@\"test record\"""#,
+    ///            command.to_command_line());
+    /// ```
+    pub fn to_command_line(&self) -> String {
+        let mut command_line = self.command.clone().into_owned();
+        for &(ref key, ref value) in &self.arguments {
+            command_line.push_str(" --");
+            command_line.push_str(key);
+            command_line.push(' ');
+            command_line.push_str(&quote_value(value));
+        }
+        if let Some(output_type) = self.get_output_type() {
+            command_line.push_str(" --output_type ");
+            command_line.push_str(&quote_value(output_type));
+        }
+        command_line
+    }
+
+    /// Parse command in URL path form or in command line form.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ruroonga_client::builtin::command_query::CommandQuery;
+    ///
+    /// let command = CommandQuery::parse("/d/select.json?table=Sites&query=a+b").unwrap();
+    /// assert_eq!(Some("json"), command.get_output_type());
+    /// assert_eq!(command, CommandQuery::parse("select Sites --query \"a b\" \
+    ///                                          --output_type json").unwrap());
+    /// ```
+    pub fn parse(command: &str) -> Result<CommandQuery<'static>, Error> {
+        let command = command.trim();
+        if command.starts_with('/') {
+            CommandQuery::from_path(command)
+        } else {
+            CommandQuery::from_command_line(command)
+        }
+    }
+
+    /// Parse command in URL path form such as `/d/select.json?table=Sites`.
+    pub fn from_path(path: &str) -> Result<CommandQuery<'static>, Error> {
+        let (path, query) = match path.find('?') {
+            Some(index) => (&path[..index], &path[index + 1..]),
+            None => (path, ""),
+        };
+        let (prefix, name) = match path.rfind('/') {
+            Some(index) => (&path[..index], &path[index + 1..]),
+            None => return Err(Error::InvalidArgument(format!("`{}` is not command path", path))),
+        };
+        let (name, output_type) = match name.find('.') {
+            Some(index) => (&name[..index], Some(&name[index + 1..])),
+            None => (name, None),
+        };
+        if name.is_empty() {
+            return Err(Error::InvalidArgument(format!("`{}` does not have command name", path)));
+        }
+        let mut command = CommandQuery::new(name.to_owned());
+        command.set_prefix(prefix.to_owned());
+        command.output_type = output_type.map(|output_type| output_type.to_owned().into());
+        command.arguments = form_urlencoded::parse(query.as_bytes())
+            .map(|(key, value)| (key.into_owned().into(), value.into_owned().into()))
+            .collect();
+        Ok(command)
+    }
+
+    /// Parse command in command line form such as `select Sites --query "a b"`.
+    ///
+    /// Positional arguments are assigned to parameters in the documented order
+    /// of the command, regardless of named arguments.
+    pub fn from_command_line(command_line: &str) -> Result<CommandQuery<'static>, Error> {
+        let mut tokens = try!(tokenize(command_line)).into_iter();
+        let name = match tokens.next() {
+            Some((name, false)) => name,
+            _ => return Err(Error::InvalidArgument("command line is empty".to_owned())),
+        };
+        let parameters = positional_parameters(&name);
+        let mut command = CommandQuery::new(name.clone());
+        let mut n_positionals = 0;
+        while let Some((token, is_quoted)) = tokens.next() {
+            if !is_quoted && token.starts_with("--") {
+                let key = token[2..].to_owned();
+                let value = match tokens.next() {
+                    Some((value, _)) => value,
+                    None => {
+                        return Err(Error::InvalidArgument(format!("`{}` does not have value",
+                                                                  token)))
+                    }
+                };
+                if key == "output_type" {
+                    command.set_output_type(value);
+                } else {
+                    command.push_argument(key, value);
+                }
+            } else {
+                let key = try!(parameters.get(n_positionals).ok_or_else(|| {
+                    Error::InvalidArgument(format!("unexpected positional argument `{}` of {}",
+                                                   token,
+                                                   name))
+                }));
+                n_positionals += 1;
+                command.push_argument(*key, token);
+            }
+        }
+        Ok(command)
+    }
+}
+
+// Escape `"`, `\` and newline in double quotes as Groonga's command line parser expects.
+fn quote_value(value: &str) -> Cow<str> {
+    let is_special = |c: char| c.is_whitespace() || c == '"' || c == '\'' || c == '\\';
+    if !value.is_empty() && !value.starts_with("--") && !value.chars().any(is_special) {
+        return Cow::Borrowed(value);
+    }
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    Cow::Owned(quoted)
+}
+
+// Split command line into tokens with whether each token is quoted.
+fn tokenize(command_line: &str) -> Result<Vec<(String, bool)>, Error> {
+    let mut tokens = vec![];
+    let mut chars = command_line.chars().peekable();
+    loop {
+        while chars.peek().map_or(false, |c| c.is_whitespace()) {
+            chars.next();
+        }
+        let quote = match chars.peek() {
+            Some(&c) if c == '"' || c == '\'' => Some(c),
+            Some(_) => None,
+            None => break,
+        };
+        let mut token = String::new();
+        if quote.is_some() {
+            chars.next();
+        }
+        let mut is_closed = quote.is_none();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    match chars.next() {
+                        Some('n') => token.push('\n'),
+                        Some(c) => token.push(c),
+                        None => break,
+                    }
+                }
+                c if Some(c) == quote => {
+                    is_closed = true;
+                    break;
+                }
+                c if quote.is_none() && c.is_whitespace() => break,
+                c => token.push(c),
+            }
+        }
+        if !is_closed {
+            return Err(Error::InvalidArgument(format!("unterminated quote in `{}`", command_line)));
+        }
+        tokens.push((token, quote.is_some()));
+    }
+    Ok(tokens)
+}
+
+// Parameters which accept positional arguments, in the documented order.
+fn positional_parameters(command: &str) -> &'static [&'static str] {
+    match command {
+        "select" => {
+            &["table",
+              "match_columns",
+              "query",
+              "filter",
+              "scorer",
+              "sortby",
+              "output_columns",
+              "offset",
+              "limit",
+              "drilldown",
+              "drilldown_sortby",
+              "drilldown_output_columns",
+              "drilldown_offset",
+              "drilldown_limit",
+              "cache",
+              "match_escalation_threshold",
+              "query_expansion",
+              "query_flags",
+              "query_expander",
+              "adjuster",
+              "drilldown_calc_types",
+              "drilldown_calc_target",
+              "drilldown_filter",
+              "sort_keys",
+              "drilldown_sort_keys"]
+        }
+        "load" => &["values", "table", "columns", "ifexists", "input_type", "each"],
+        "table_create" => {
+            &["name",
+              "flags",
+              "key_type",
+              "value_type",
+              "default_tokenizer",
+              "normalizer",
+              "token_filters"]
+        }
+        "column_create" => &["table", "name", "flags", "type", "source"],
+        "table_remove" => &["name"],
+        "column_remove" => &["table", "name"],
+        "column_list" => &["table"],
+        "delete" => &["table", "key", "id", "filter"],
+        "truncate" => &["target_name"],
+        _ => &[],
+    }
 }
 
 #[cfg(test)]
@@ -96,4 +365,114 @@ mod tests {
                            org%2F%22%27";
         assert_eq!(url_encoded, command.encode());
     }
+
+    #[test]
+    fn construct_query_with_pushed_argument() {
+        let mut command = CommandQuery::new("select");
+        command.set_argument(vec![("table", "Site")]);
+        command.push_argument("limit", 5.to_string());
+        let url_encoded = "/d/select?table=Site&limit=5";
+        assert_eq!(url_encoded, command.encode());
+    }
+
+    #[test]
+    fn construct_command_line() {
+        let mut command = CommandQuery::new("select");
+        command.set_argument(vec![("table", "Site"), ("output_columns", "_key,title")]);
+        assert_eq!("select --table Site --output_columns _key,title",
+                   command.to_command_line());
+    }
+
+    #[test]
+    fn construct_command_line_with_escaped_values() {
+        let mut command = CommandQuery::new("load");
+        command.set_argument(vec![("table", "Site"),
+                                  ("values", "[{\"_key\":\"a b\",\n\"path\":\"C:\\\\\"}]"),
+                                  ("filter", "'quoted'"),
+                                  ("ifexists", ""),
+                                  ("query", "--not-option")]);
+        let command_line = concat!(r#"load --table Site "#,
+                                   r#"--values "[{\"_key\":\"a b\",\n\"path\":\"C:\\\\\"}]" "#,
+                                   r#"--filter "'quoted'" --ifexists "" --query "--not-option""#);
+        assert_eq!(command_line, command.to_command_line());
+    }
+
+    #[test]
+    fn parse_path() {
+        let command = CommandQuery::from_path("/d/select.json?table=Site&query=%27a+b%27").unwrap();
+        assert_eq!("select", command.get_command());
+        assert_eq!(Some("json"), command.get_output_type());
+        assert_eq!("/d", command.get_prefix());
+        assert_eq!(Some("'a b'"), command.get_argument("query"));
+        assert_eq!("status", CommandQuery::parse("/d/status").unwrap().get_command());
+    }
+
+    #[test]
+    fn parse_command_line_with_positional_arguments() {
+        let command = CommandQuery::parse("column_create Terms sites_title COLUMN_INDEX \
+                                           --source title Sites")
+            .unwrap();
+        assert_eq!("table=Terms&name=sites_title&flags=COLUMN_INDEX&source=title&type=Sites",
+                   command.make_query());
+        assert_eq!(Some("a b\n'c'\\"),
+                   CommandQuery::parse(r#"select Site --query 'a b\n\'c\'\\'"#)
+                       .unwrap()
+                       .get_argument("query"));
+    }
+
+    #[test]
+    fn reject_malformed_commands() {
+        for command in &["", "select Site --query", "select --query \"a b", "status Site",
+                         "/d/?table=Site"] {
+            match CommandQuery::parse(command) {
+                Err(Error::InvalidArgument(_)) => (),
+                _ => panic!("`{}` should be rejected", command),
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_with_encoders() {
+        let values = vec!["Site", "", "a b", "\"quoted\"", "'single'", "back\\slash\\",
+                          "new\nline", "--option", "&=%+?#", "日本語", " \t ", "\\n"];
+        for (i, value) in values.iter().enumerate() {
+            for other in &values[i..] {
+                let mut command = CommandQuery::new("select");
+                command.push_argument("table", "Site");
+                command.push_argument("query", *value);
+                command.push_argument("filter", *other);
+                if i % 2 == 0 {
+                    command.set_output_type("json");
+                }
+                assert_eq!(command, CommandQuery::parse(&command.encode()).unwrap());
+                assert_eq!(command,
+                           CommandQuery::parse(&command.to_command_line()).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_with_generated_values() {
+        // Every sequence of up to four fragments, so that spaces, quotes,
+        // backslashes and newlines are mixed in one value.
+        let fragments = ["", " ", "\"", "'", "\\", "\n", "a", "--"];
+        let mut values = vec![String::new()];
+        let mut generated = values.clone();
+        for _ in 0..4 {
+            generated = generated.iter()
+                .flat_map(|value| fragments.iter().map(move |fragment| value.clone() + fragment))
+                .collect();
+            values.extend(generated.iter().cloned());
+        }
+        for value in &values {
+            let reversed = value.chars().rev().collect::<String>();
+            let mut command = CommandQuery::new("select");
+            command.push_argument("query", value);
+            command.push_argument("filter", &reversed);
+            command.push_argument("match_columns", "");
+            assert_eq!(command, CommandQuery::parse(&command.encode()).unwrap());
+            assert_eq!(command,
+                       CommandQuery::parse(&command.to_command_line()).unwrap());
+        }
+    }
 }
@@ -0,0 +1,210 @@
+use std::borrow::Cow;
+use std::fmt;
+use builtin::command_query::CommandQuery;
+
+/// Calculation types of drilldown, a.k.a. `calc_types`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CalcType {
+    /// `COUNT`
+    Count,
+    /// `MAX`
+    Max,
+    /// `MIN`
+    Min,
+    /// `SUM`
+    Sum,
+    /// `AVG`
+    Avg,
+}
+
+impl fmt::Display for CalcType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let calc_type = match *self {
+            CalcType::Count => "COUNT",
+            CalcType::Max => "MAX",
+            CalcType::Min => "MIN",
+            CalcType::Sum => "SUM",
+            CalcType::Avg => "AVG",
+        };
+        write!(f, "{}", calc_type)
+    }
+}
+
+/// Builder of drilldown which is plugged into `Select`.
+///
+/// # Examples
+///
+/// ```
+/// use ruroonga_client::builtin::drilldown::{CalcType, Drilldown};
+/// use ruroonga_client::builtin::select::Select;
+///
+/// let command = Select::new("Entries")
+///     .labeled_drilldown("tag",
+///                        Drilldown::new(vec!["tag"])
+///                            .calc_type(CalcType::Sum)
+///                            .calc_target("n_likes")
+///                            .sort_keys(vec!["-_nsubrecs"]))
+///     .build();
+/// assert_eq!("table=Entries&drilldowns%5Btag%5D.keys=tag&\
+///             drilldowns%5Btag%5D.sort_keys=-_nsubrecs&\
+///             drilldowns%5Btag%5D.calc_types=SUM&\
+///             drilldowns%5Btag%5D.calc_target=n_likes",
+///            command.make_query());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Drilldown<'a> {
+    keys: Vec<Cow<'a, str>>,
+    sort_keys: Vec<Cow<'a, str>>,
+    output_columns: Vec<Cow<'a, str>>,
+    offset: Option<i64>,
+    limit: Option<i64>,
+    calc_types: Vec<CalcType>,
+    calc_target: Option<Cow<'a, str>>,
+    filter: Option<Cow<'a, str>>,
+}
+
+impl<'a> Drilldown<'a> {
+    /// Create drilldown builder which groups records by the specified keys.
+    pub fn new<I, T>(keys: I) -> Drilldown<'a>
+        where I: IntoIterator<Item = T>,
+              T: Into<Cow<'a, str>>
+    {
+        Drilldown {
+            keys: keys.into_iter().map(|key| key.into()).collect(),
+            sort_keys: vec![],
+            output_columns: vec![],
+            offset: None,
+            limit: None,
+            calc_types: vec![],
+            calc_target: None,
+            filter: None,
+        }
+    }
+
+    /// Set sort keys of drilldown result.
+    pub fn sort_keys<I, T>(mut self, keys: I) -> Drilldown<'a>
+        where I: IntoIterator<Item = T>,
+              T: Into<Cow<'a, str>>
+    {
+        self.sort_keys = keys.into_iter().map(|key| key.into()).collect();
+        self
+    }
+
+    /// Set output columns of drilldown result.
+    pub fn output_columns<I, T>(mut self, columns: I) -> Drilldown<'a>
+        where I: IntoIterator<Item = T>,
+              T: Into<Cow<'a, str>>
+    {
+        self.output_columns = columns.into_iter().map(|column| column.into()).collect();
+        self
+    }
+
+    /// Set offset of drilldown result.
+    pub fn offset(mut self, offset: i64) -> Drilldown<'a> {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Set the maximum number of drilldown result.
+    pub fn limit(mut self, limit: i64) -> Drilldown<'a> {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Append calculation type for `calc_target`.
+    pub fn calc_type(mut self, calc_type: CalcType) -> Drilldown<'a> {
+        self.calc_types.push(calc_type);
+        self
+    }
+
+    /// Set column which is calculated by `calc_type`.
+    pub fn calc_target<T>(mut self, column: T) -> Drilldown<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.calc_target = Some(column.into());
+        self
+    }
+
+    /// Set filter for drilldown result.
+    pub fn filter<T>(mut self, filter: T) -> Drilldown<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.filter = Some(filter.into());
+        self
+    }
+
+    #[doc(hidden)]
+    // Push `drilldown_*` arguments, or `drilldowns[label].*` arguments
+    // if label is specified. This function is mainly provided for `Select`.
+    pub fn push_arguments(self, command: &mut CommandQuery<'a>, label: Option<&str>) {
+        let name = |parameter: &str| -> String {
+            match label {
+                Some(label) => format!("drilldowns[{}].{}", label, parameter),
+                None if parameter == "keys" => "drilldown".to_owned(),
+                None => format!("drilldown_{}", parameter),
+            }
+        };
+        command.push_argument(name("keys"), self.keys.join(","));
+        if !self.sort_keys.is_empty() {
+            command.push_argument(name("sort_keys"), self.sort_keys.join(","));
+        }
+        if !self.output_columns.is_empty() {
+            command.push_argument(name("output_columns"), self.output_columns.join(","));
+        }
+        if let Some(offset) = self.offset {
+            command.push_argument(name("offset"), offset.to_string());
+        }
+        if let Some(limit) = self.limit {
+            command.push_argument(name("limit"), limit.to_string());
+        }
+        if !self.calc_types.is_empty() {
+            let calc_types = self.calc_types
+                .iter()
+                .map(|calc_type| calc_type.to_string())
+                .collect::<Vec<_>>()
+                .join("|");
+            command.push_argument(name("calc_types"), calc_types);
+        }
+        if let Some(calc_target) = self.calc_target {
+            command.push_argument(name("calc_target"), calc_target);
+        }
+        if let Some(filter) = self.filter {
+            command.push_argument(name("filter"), filter);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_plain_drilldown() {
+        let mut command = CommandQuery::new("select");
+        Drilldown::new(vec!["tag", "author"])
+            .sort_keys(vec!["-_nsubrecs"])
+            .output_columns(vec!["_key", "_nsubrecs"])
+            .offset(0)
+            .limit(10)
+            .push_arguments(&mut command, None);
+        assert_eq!("drilldown=tag%2Cauthor&drilldown_sort_keys=-_nsubrecs&\
+                    drilldown_output_columns=_key%2C_nsubrecs&drilldown_offset=0&\
+                    drilldown_limit=10",
+                   command.make_query());
+    }
+
+    #[test]
+    fn push_labeled_drilldown() {
+        let mut command = CommandQuery::new("select");
+        Drilldown::new(vec!["tag"])
+            .calc_type(CalcType::Max)
+            .calc_type(CalcType::Avg)
+            .calc_target("n_likes")
+            .filter("_nsubrecs > 1")
+            .push_arguments(&mut command, Some("tag"));
+        assert_eq!("drilldowns%5Btag%5D.keys=tag&drilldowns%5Btag%5D.calc_types=MAX%7CAVG&\
+                    drilldowns%5Btag%5D.calc_target=n_likes&\
+                    drilldowns%5Btag%5D.filter=_nsubrecs+%3E+1",
+                   command.make_query());
+    }
+}
@@ -0,0 +1,252 @@
+use std::borrow::Cow;
+use serde::Serialize;
+use serde_json;
+use serde_json::Value;
+use builtin::command_query::CommandQuery;
+use error::Error;
+
+/// Typed builder of `load` command.
+///
+/// Records are serialized as JSON objects, or as JSON arrays
+/// if `columns` is specified. Records which are already set are checked
+/// again when `columns` is specified later.
+///
+/// # Examples
+///
+/// ```
+/// extern crate ruroonga_client;
+/// #[macro_use]
+/// extern crate serde_derive;
+///
+/// use ruroonga_client::builtin::load::Load;
+///
+/// #[derive(Serialize)]
+/// struct Site {
+///     #[serde(rename = "_key")]
+///     key: String,
+///     title: String,
+/// }
+///
+/// # fn main() {
+/// let sites = vec![Site {
+///                      key: "http://example.org/".to_owned(),
+///                      title: "This is test record 1!".to_owned(),
+///                  }];
+/// let load = Load::new("Sites").values(sites).unwrap().output_errors(true);
+/// assert_eq!("/d/load?table=Sites&output_errors=yes&command_version=3",
+///            load.command().encode());
+/// assert_eq!(r#"[{"_key":"http://example.org/","title":"This is test record 1!"}]"#,
+///            load.body());
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Load<'a> {
+    table: Cow<'a, str>,
+    columns: Vec<Cow<'a, str>>,
+    values: String,
+    ifexists: Option<Cow<'a, str>>,
+    output_ids: bool,
+    output_errors: bool,
+}
+
+impl<'a> Load<'a> {
+    /// Create `load` command builder for the specified table.
+    pub fn new<T>(table: T) -> Load<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        Load {
+            table: table.into(),
+            columns: vec![],
+            values: "[]".to_owned(),
+            ifexists: None,
+            output_ids: false,
+            output_errors: false,
+        }
+    }
+
+    /// Set columns of values in array form.
+    ///
+    /// This method returns `Error::Serialize` if records are already set
+    /// and they do not match the columns.
+    pub fn columns<I, T>(mut self, columns: I) -> Result<Load<'a>, Error>
+        where I: IntoIterator<Item = T>,
+              T: Into<Cow<'a, str>>
+    {
+        self.columns = columns.into_iter().map(|column| column.into()).collect();
+        // Only serialized values are kept, so they are parsed again to check.
+        let records: Vec<Value> = try!(serde_json::from_str(&self.values)
+            .map_err(|err| Error::Serialize(format!("{}", err))));
+        for (index, record) in records.iter().enumerate() {
+            try!(check_record(index, record, &self.columns));
+        }
+        Ok(self)
+    }
+
+    /// Serialize records to load.
+    ///
+    /// This method returns `Error::Serialize` if a record is not serialized
+    /// into an object, or into an array when `columns` is specified.
+    pub fn values<I, T>(mut self, records: I) -> Result<Load<'a>, Error>
+        where I: IntoIterator<Item = T>,
+              T: Serialize
+    {
+        let mut values = "[".to_owned();
+        for (index, record) in records.into_iter().enumerate() {
+            let value = try!(serde_json::to_value(record)
+                .map_err(|err| Error::Serialize(format!("record {}: {}", index, err))));
+            try!(check_record(index, &value, &self.columns));
+            if index > 0 {
+                values.push(',');
+            }
+            values.push_str(&try!(serde_json::to_string(&value)
+                .map_err(|err| Error::Serialize(format!("record {}: {}", index, err)))));
+        }
+        values.push(']');
+        self.values = values;
+        Ok(self)
+    }
+
+    /// Set condition to update an existing record, a.k.a. `ifexists`.
+    pub fn ifexists<T>(mut self, condition: T) -> Load<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.ifexists = Some(condition.into());
+        self
+    }
+
+    /// Set whether Groonga reports IDs of loaded records.
+    ///
+    /// It requires `command_version=3` and is added automatically.
+    pub fn output_ids(mut self, output_ids: bool) -> Load<'a> {
+        self.output_ids = output_ids;
+        self
+    }
+
+    /// Set whether Groonga reports errors of each record.
+    ///
+    /// It requires `command_version=3` and is added automatically.
+    pub fn output_errors(mut self, output_errors: bool) -> Load<'a> {
+        self.output_errors = output_errors;
+        self
+    }
+
+    /// Build `load` command query without values.
+    pub fn command(&self) -> CommandQuery<'a> {
+        let mut command = CommandQuery::new("load");
+        command.push_argument("table", self.table.clone());
+        if !self.columns.is_empty() {
+            command.push_argument("columns", self.columns.join(","));
+        }
+        if let Some(ref ifexists) = self.ifexists {
+            command.push_argument("ifexists", ifexists.clone());
+        }
+        if self.output_ids {
+            command.push_argument("output_ids", "yes");
+        }
+        if self.output_errors {
+            command.push_argument("output_errors", "yes");
+        }
+        if self.output_ids || self.output_errors {
+            command.push_argument("command_version", "3");
+        }
+        command
+    }
+
+    /// Return serialized values which is sent as request body.
+    pub fn body(&self) -> &str {
+        &self.values
+    }
+}
+
+// Record is an object, or an array which has a value for each column.
+fn check_record(index: usize, record: &Value, columns: &[Cow<str>]) -> Result<(), Error> {
+    match *record {
+        Value::Object(_) if columns.is_empty() => Ok(()),
+        Value::Array(ref array) if !columns.is_empty() => {
+            if array.len() != columns.len() {
+                return Err(Error::Serialize(format!("record {} has {} values but {} columns \
+                                                     are specified",
+                                                    index,
+                                                    array.len(),
+                                                    columns.len())));
+            }
+            Ok(())
+        }
+        _ if columns.is_empty() => Err(Error::Serialize(format!("record {} is not object", index))),
+        _ => Err(Error::Serialize(format!("record {} is not array", index))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Site<'a> {
+        #[serde(rename = "_key")]
+        key: &'a str,
+        title: &'a str,
+    }
+
+    #[test]
+    fn load_objects() {
+        let load = Load::new("Sites")
+            .values(vec![Site {
+                             key: "http://example.org/",
+                             title: "test record 1",
+                         },
+                         Site {
+                             key: "http://example.net/",
+                             title: "test record 2",
+                         }])
+            .unwrap()
+            .ifexists("true");
+        assert_eq!("/d/load?table=Sites&ifexists=true", load.command().encode());
+        assert_eq!(concat!(r#"[{"_key":"http://example.org/","title":"test record 1"},"#,
+                           r#"{"_key":"http://example.net/","title":"test record 2"}]"#),
+                   load.body());
+    }
+
+    #[test]
+    fn load_arrays_with_columns() {
+        let load = Load::new("Sites")
+            .columns(vec!["_key", "n_likes"])
+            .unwrap()
+            .values(vec![("http://example.org/", 1), ("http://example.net/", 2)])
+            .unwrap()
+            .output_ids(true);
+        assert_eq!("table=Sites&columns=_key%2Cn_likes&output_ids=yes&command_version=3",
+                   load.command().make_query());
+        assert_eq!(r#"[["http://example.org/",1],["http://example.net/",2]]"#,
+                   load.body());
+    }
+
+    #[test]
+    fn reject_records_in_unexpected_form() {
+        match Load::new("Sites").values(vec![("http://example.org/", 1)]) {
+            Err(Error::Serialize(message)) => assert_eq!("record 0 is not object", message),
+            _ => panic!("array record without columns should be rejected"),
+        }
+        match Load::new("Sites")
+            .columns(vec!["_key"])
+            .unwrap()
+            .values(vec![("http://example.org/", 1)]) {
+            Err(Error::Serialize(message)) => {
+                assert_eq!("record 0 has 2 values but 1 columns are specified", message)
+            }
+            _ => panic!("record with extra values should be rejected"),
+        }
+    }
+
+    #[test]
+    fn check_records_when_columns_are_set_later() {
+        let site = Site {
+            key: "http://example.org/",
+            title: "test record 1",
+        };
+        match Load::new("Sites").values(vec![site]).unwrap().columns(vec!["_key", "title"]) {
+            Err(Error::Serialize(message)) => assert_eq!("record 0 is not array", message),
+            _ => panic!("object record with columns should be rejected"),
+        }
+    }
+}
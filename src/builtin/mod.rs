@@ -1 +1,6 @@
+pub mod column_create;
 pub mod command_query;
+pub mod drilldown;
+pub mod load;
+pub mod select;
+pub mod table_create;
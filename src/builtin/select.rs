@@ -0,0 +1,343 @@
+use std::borrow::Cow;
+use std::fmt;
+use builtin::command_query::CommandQuery;
+use builtin::drilldown::Drilldown;
+
+/// Flags of `query_flags` argument.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QueryFlag {
+    /// `ALLOW_PRAGMA`
+    AllowPragma,
+    /// `ALLOW_COLUMN`
+    AllowColumn,
+    /// `ALLOW_UPDATE`
+    AllowUpdate,
+    /// `ALLOW_LEADING_NOT`
+    AllowLeadingNot,
+    /// `QUERY_NO_SYNTAX_ERROR`
+    QueryNoSyntaxError,
+    /// `NONE`
+    None,
+}
+
+impl fmt::Display for QueryFlag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flag = match *self {
+            QueryFlag::AllowPragma => "ALLOW_PRAGMA",
+            QueryFlag::AllowColumn => "ALLOW_COLUMN",
+            QueryFlag::AllowUpdate => "ALLOW_UPDATE",
+            QueryFlag::AllowLeadingNot => "ALLOW_LEADING_NOT",
+            QueryFlag::QueryNoSyntaxError => "QUERY_NO_SYNTAX_ERROR",
+            QueryFlag::None => "NONE",
+        };
+        write!(f, "{}", flag)
+    }
+}
+
+/// Typed builder of `select` command.
+///
+/// # Examples
+///
+/// ```
+/// use ruroonga_client::builtin::select::Select;
+///
+/// let command = Select::new("Sites")
+///     .match_column_with_weight("title", 10)
+///     .match_column("body")
+///     .query("groonga")
+///     .sort_keys(vec!["-_score", "_key"])
+///     .output_columns(vec!["_key", "title", "_score"])
+///     .limit(5)
+///     .build();
+/// assert_eq!("/d/select?table=Sites&match_columns=title+*+10+%7C%7C+body&query=groonga&\
+///             sort_keys=-_score%2C_key&output_columns=_key%2Ctitle%2C_score&limit=5",
+///            command.encode());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Select<'a> {
+    table: Cow<'a, str>,
+    match_columns: Vec<(Cow<'a, str>, Option<i32>)>,
+    query: Option<Cow<'a, str>>,
+    filter: Option<Cow<'a, str>>,
+    scorer: Option<Cow<'a, str>>,
+    sort_keys: Vec<Cow<'a, str>>,
+    output_columns: Vec<Cow<'a, str>>,
+    offset: Option<i64>,
+    limit: Option<i64>,
+    cache: Option<bool>,
+    match_escalation_threshold: Option<i64>,
+    query_flags: Vec<QueryFlag>,
+    query_expander: Option<Cow<'a, str>>,
+    drilldown: Option<Drilldown<'a>>,
+    labeled_drilldowns: Vec<(Cow<'a, str>, Drilldown<'a>)>,
+}
+
+impl<'a> Select<'a> {
+    /// Create `select` command builder for the specified table.
+    pub fn new<T>(table: T) -> Select<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        Select {
+            table: table.into(),
+            match_columns: vec![],
+            query: None,
+            filter: None,
+            scorer: None,
+            sort_keys: vec![],
+            output_columns: vec![],
+            offset: None,
+            limit: None,
+            cache: None,
+            match_escalation_threshold: None,
+            query_flags: vec![],
+            query_expander: None,
+            drilldown: None,
+            labeled_drilldowns: vec![],
+        }
+    }
+
+    /// Append a column which is searched by `query`.
+    pub fn match_column<T>(mut self, column: T) -> Select<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.match_columns.push((column.into(), None));
+        self
+    }
+
+    /// Append a column which is searched by `query` with its weight.
+    ///
+    /// Negative weight decreases score of records which match the column.
+    pub fn match_column_with_weight<T>(mut self, column: T, weight: i32) -> Select<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.match_columns.push((column.into(), Some(weight)));
+        self
+    }
+
+    /// Set query in Groonga's query syntax.
+    pub fn query<T>(mut self, query: T) -> Select<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.query = Some(query.into());
+        self
+    }
+
+    /// Set filter in Groonga's script syntax.
+    pub fn filter<T>(mut self, filter: T) -> Select<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.filter = Some(filter.into());
+        self
+    }
+
+    /// Set scorer expression.
+    pub fn scorer<T>(mut self, scorer: T) -> Select<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.scorer = Some(scorer.into());
+        self
+    }
+
+    /// Set sort keys. Prefix `-` means descending order.
+    pub fn sort_keys<I, T>(mut self, keys: I) -> Select<'a>
+        where I: IntoIterator<Item = T>,
+              T: Into<Cow<'a, str>>
+    {
+        self.sort_keys = keys.into_iter().map(|key| key.into()).collect();
+        self
+    }
+
+    /// Set output columns.
+    pub fn output_columns<I, T>(mut self, columns: I) -> Select<'a>
+        where I: IntoIterator<Item = T>,
+              T: Into<Cow<'a, str>>
+    {
+        self.output_columns = columns.into_iter().map(|column| column.into()).collect();
+        self
+    }
+
+    /// Set offset of output records.
+    pub fn offset(mut self, offset: i64) -> Select<'a> {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Set the maximum number of output records. `-1` outputs all records.
+    pub fn limit(mut self, limit: i64) -> Select<'a> {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Set whether Groonga caches the result.
+    pub fn cache(mut self, cache: bool) -> Select<'a> {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Set threshold to escalate search method.
+    pub fn match_escalation_threshold(mut self, threshold: i64) -> Select<'a> {
+        self.match_escalation_threshold = Some(threshold);
+        self
+    }
+
+    /// Append a flag of `query`.
+    pub fn query_flag(mut self, flag: QueryFlag) -> Select<'a> {
+        self.query_flags.push(flag);
+        self
+    }
+
+    /// Set query expander, such as `QueryExpanderTSV`.
+    pub fn query_expander<T>(mut self, expander: T) -> Select<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.query_expander = Some(expander.into());
+        self
+    }
+
+    /// Set drilldown, a.k.a. `drilldown` and `drilldown_*` arguments.
+    pub fn drilldown(mut self, drilldown: Drilldown<'a>) -> Select<'a> {
+        self.drilldown = Some(drilldown);
+        self
+    }
+
+    /// Append labeled drilldown, a.k.a. `drilldowns[label].*` arguments.
+    pub fn labeled_drilldown<T>(mut self, label: T, drilldown: Drilldown<'a>) -> Select<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.labeled_drilldowns.push((label.into(), drilldown));
+        self
+    }
+
+    /// Build `select` command query.
+    pub fn build(self) -> CommandQuery<'a> {
+        let mut command = CommandQuery::new("select");
+        command.push_argument("table", self.table);
+        if !self.match_columns.is_empty() {
+            let match_columns = self.match_columns
+                .iter()
+                .map(|&(ref column, weight)| match weight {
+                    Some(weight) => format!("{} * {}", column, weight),
+                    None => column.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" || ");
+            command.push_argument("match_columns", match_columns);
+        }
+        if let Some(query) = self.query {
+            command.push_argument("query", query);
+        }
+        if let Some(filter) = self.filter {
+            command.push_argument("filter", filter);
+        }
+        if let Some(scorer) = self.scorer {
+            command.push_argument("scorer", scorer);
+        }
+        if !self.sort_keys.is_empty() {
+            command.push_argument("sort_keys", self.sort_keys.join(","));
+        }
+        if !self.output_columns.is_empty() {
+            command.push_argument("output_columns", self.output_columns.join(","));
+        }
+        if let Some(offset) = self.offset {
+            command.push_argument("offset", offset.to_string());
+        }
+        if let Some(limit) = self.limit {
+            command.push_argument("limit", limit.to_string());
+        }
+        if let Some(cache) = self.cache {
+            command.push_argument("cache", if cache { "yes" } else { "no" });
+        }
+        if let Some(threshold) = self.match_escalation_threshold {
+            command.push_argument("match_escalation_threshold", threshold.to_string());
+        }
+        if !self.query_flags.is_empty() {
+            let query_flags = self.query_flags
+                .iter()
+                .map(|flag| flag.to_string())
+                .collect::<Vec<_>>()
+                .join("|");
+            command.push_argument("query_flags", query_flags);
+        }
+        if let Some(expander) = self.query_expander {
+            command.push_argument("query_expander", expander);
+        }
+        if let Some(drilldown) = self.drilldown {
+            drilldown.push_arguments(&mut command, None);
+        }
+        for (label, drilldown) in self.labeled_drilldowns {
+            drilldown.push_arguments(&mut command, Some(&label));
+        }
+        command
+    }
+}
+
+impl<'a> From<Select<'a>> for CommandQuery<'a> {
+    fn from(select: Select<'a>) -> CommandQuery<'a> {
+        select.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn construct_select() {
+        let command = Select::new("Sites").build();
+        assert_eq!("/d/select?table=Sites", command.encode());
+    }
+
+    #[test]
+    fn construct_select_with_all_arguments() {
+        let command = Select::new("Sites")
+            .match_column("title")
+            .query("groonga")
+            .filter("_id < 5")
+            .scorer("_score = _score * 2")
+            .sort_keys(vec!["-_score"])
+            .output_columns(vec!["_key", "_score"])
+            .offset(10)
+            .limit(-1)
+            .cache(false)
+            .match_escalation_threshold(-1)
+            .query_flag(QueryFlag::AllowColumn)
+            .query_flag(QueryFlag::AllowLeadingNot)
+            .query_expander("QueryExpanderTSV")
+            .build();
+        let url_encoded = "/d/select?table=Sites&match_columns=title&query=groonga&\
+                           filter=_id+%3C+5&scorer=_score+%3D+_score+*+2&sort_keys=-_score&\
+                           output_columns=_key%2C_score&offset=10&limit=-1&cache=no&\
+                           match_escalation_threshold=-1&\
+                           query_flags=ALLOW_COLUMN%7CALLOW_LEADING_NOT&\
+                           query_expander=QueryExpanderTSV";
+        assert_eq!(url_encoded, command.encode());
+    }
+
+    #[test]
+    fn construct_select_with_drilldown() {
+        let command = Select::new("Sites")
+            .drilldown(Drilldown::new(vec!["tag"]).limit(5))
+            .build();
+        assert_eq!("table=Sites&drilldown=tag&drilldown_limit=5",
+                   command.make_query());
+    }
+
+    #[test]
+    fn construct_select_with_negative_weight() {
+        let command = Select::new("Sites")
+            .match_column_with_weight("title", 10)
+            .match_column_with_weight("body", -5)
+            .query("groonga")
+            .build();
+        assert_eq!("table=Sites&match_columns=title+*+10+%7C%7C+body+*+-5&query=groonga",
+                   command.make_query());
+    }
+
+    #[test]
+    fn convert_into_command_query() {
+        let owned_table = "Sites".to_owned();
+        let command: CommandQuery = Select::new(owned_table).limit(1).into();
+        assert_eq!("select", command.get_command());
+        assert_eq!("table=Sites&limit=1", command.make_query());
+    }
+}
@@ -0,0 +1,244 @@
+use std::borrow::Cow;
+use std::fmt;
+use builtin::command_query::CommandQuery;
+use error::Error;
+
+/// Type of table, a.k.a. `TABLE_*` flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TableType {
+    /// `TABLE_HASH_KEY`
+    HashKey,
+    /// `TABLE_PAT_KEY`
+    PatKey,
+    /// `TABLE_DAT_KEY`
+    DatKey,
+    /// `TABLE_NO_KEY`
+    NoKey,
+}
+
+impl fmt::Display for TableType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let table_type = match *self {
+            TableType::HashKey => "TABLE_HASH_KEY",
+            TableType::PatKey => "TABLE_PAT_KEY",
+            TableType::DatKey => "TABLE_DAT_KEY",
+            TableType::NoKey => "TABLE_NO_KEY",
+        };
+        write!(f, "{}", table_type)
+    }
+}
+
+/// Typed builder of `table_create` command.
+///
+/// # Examples
+///
+/// ```
+/// use ruroonga_client::builtin::table_create::{TableCreate, TableType};
+///
+/// let command = TableCreate::new("Terms", TableType::PatKey)
+///     .key_type("ShortText")
+///     .default_tokenizer("TokenBigram")
+///     .normalizer("NormalizerAuto")
+///     .build()
+///     .unwrap();
+/// assert_eq!("/d/table_create?name=Terms&flags=TABLE_PAT_KEY&key_type=ShortText&\
+///             default_tokenizer=TokenBigram&normalizer=NormalizerAuto",
+///            command.encode());
+/// ```
+#[derive(Clone, Debug)]
+pub struct TableCreate<'a> {
+    name: Cow<'a, str>,
+    table_type: TableType,
+    key_normalize: bool,
+    key_with_sis: bool,
+    key_type: Option<Cow<'a, str>>,
+    value_type: Option<Cow<'a, str>>,
+    default_tokenizer: Option<Cow<'a, str>>,
+    normalizer: Option<Cow<'a, str>>,
+    token_filters: Vec<Cow<'a, str>>,
+}
+
+impl<'a> TableCreate<'a> {
+    /// Create `table_create` command builder.
+    pub fn new<T>(name: T, table_type: TableType) -> TableCreate<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        TableCreate {
+            name: name.into(),
+            table_type: table_type,
+            key_normalize: false,
+            key_with_sis: false,
+            key_type: None,
+            value_type: None,
+            default_tokenizer: None,
+            normalizer: None,
+            token_filters: vec![],
+        }
+    }
+
+    /// Set `KEY_NORMALIZE` flag. Use `normalizer` for new tables instead.
+    pub fn key_normalize(mut self, key_normalize: bool) -> TableCreate<'a> {
+        self.key_normalize = key_normalize;
+        self
+    }
+
+    /// Set `KEY_WITH_SIS` flag. It is only for `TABLE_PAT_KEY`.
+    pub fn key_with_sis(mut self, key_with_sis: bool) -> TableCreate<'a> {
+        self.key_with_sis = key_with_sis;
+        self
+    }
+
+    /// Set type of key. It is required except `TABLE_NO_KEY`.
+    pub fn key_type<T>(mut self, key_type: T) -> TableCreate<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.key_type = Some(key_type.into());
+        self
+    }
+
+    /// Set type of value.
+    pub fn value_type<T>(mut self, value_type: T) -> TableCreate<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.value_type = Some(value_type.into());
+        self
+    }
+
+    /// Set tokenizer, such as `TokenBigram`.
+    pub fn default_tokenizer<T>(mut self, tokenizer: T) -> TableCreate<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.default_tokenizer = Some(tokenizer.into());
+        self
+    }
+
+    /// Set normalizer, such as `NormalizerAuto`.
+    pub fn normalizer<T>(mut self, normalizer: T) -> TableCreate<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.normalizer = Some(normalizer.into());
+        self
+    }
+
+    /// Set token filters, such as `TokenFilterStopWord`.
+    pub fn token_filters<I, T>(mut self, token_filters: I) -> TableCreate<'a>
+        where I: IntoIterator<Item = T>,
+              T: Into<Cow<'a, str>>
+    {
+        self.token_filters = token_filters.into_iter().map(|filter| filter.into()).collect();
+        self
+    }
+
+    // Reject combinations which Groonga does not accept.
+    fn validate(&self) -> Result<(), Error> {
+        let invalid = |message: &str| {
+            Err(Error::InvalidArgument(format!("{}: {} {}", self.name, self.table_type, message)))
+        };
+        match self.table_type {
+            TableType::NoKey => {
+                if self.key_type.is_some() {
+                    return invalid("cannot have key_type");
+                }
+                if self.key_normalize {
+                    return invalid("cannot have KEY_NORMALIZE");
+                }
+                if self.default_tokenizer.is_some() || self.normalizer.is_some() ||
+                   !self.token_filters.is_empty() {
+                    return invalid("cannot have tokenizer, normalizer nor token filters");
+                }
+            }
+            _ => {
+                if self.key_type.is_none() {
+                    return invalid("requires key_type");
+                }
+            }
+        }
+        if self.key_with_sis && self.table_type != TableType::PatKey {
+            return invalid("cannot have KEY_WITH_SIS");
+        }
+        if self.value_type.is_some() && self.table_type == TableType::DatKey {
+            return invalid("cannot have value_type");
+        }
+        Ok(())
+    }
+
+    /// Build `table_create` command query.
+    ///
+    /// This method returns `Error::InvalidArgument` for invalid combination
+    /// of table type, flags and arguments.
+    pub fn build(self) -> Result<CommandQuery<'a>, Error> {
+        try!(self.validate());
+        let mut flags = vec![self.table_type.to_string()];
+        if self.key_normalize {
+            flags.push("KEY_NORMALIZE".to_owned());
+        }
+        if self.key_with_sis {
+            flags.push("KEY_WITH_SIS".to_owned());
+        }
+        let mut command = CommandQuery::new("table_create");
+        command.push_argument("name", self.name);
+        command.push_argument("flags", flags.join("|"));
+        if let Some(key_type) = self.key_type {
+            command.push_argument("key_type", key_type);
+        }
+        if let Some(value_type) = self.value_type {
+            command.push_argument("value_type", value_type);
+        }
+        if let Some(tokenizer) = self.default_tokenizer {
+            command.push_argument("default_tokenizer", tokenizer);
+        }
+        if let Some(normalizer) = self.normalizer {
+            command.push_argument("normalizer", normalizer);
+        }
+        if !self.token_filters.is_empty() {
+            command.push_argument("token_filters", self.token_filters.join(","));
+        }
+        Ok(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn construct_hash_table() {
+        let command = TableCreate::new("Sites", TableType::HashKey)
+            .key_type("ShortText")
+            .key_normalize(true)
+            .build()
+            .unwrap();
+        assert_eq!("/d/table_create?name=Sites&flags=TABLE_HASH_KEY%7CKEY_NORMALIZE&\
+                    key_type=ShortText",
+                   command.encode());
+    }
+
+    #[test]
+    fn construct_no_key_table() {
+        let command = TableCreate::new("Logs", TableType::NoKey)
+            .value_type("UInt32")
+            .build()
+            .unwrap();
+        assert_eq!("name=Logs&flags=TABLE_NO_KEY&value_type=UInt32",
+                   command.make_query());
+    }
+
+    #[test]
+    fn reject_invalid_combinations() {
+        let invalid = vec![TableCreate::new("Logs", TableType::NoKey).key_type("ShortText"),
+                           TableCreate::new("Logs", TableType::NoKey).normalizer("NormalizerAuto"),
+                           TableCreate::new("Sites", TableType::HashKey),
+                           TableCreate::new("Sites", TableType::HashKey)
+                               .key_type("ShortText")
+                               .key_with_sis(true),
+                           TableCreate::new("Sites", TableType::DatKey)
+                               .key_type("ShortText")
+                               .value_type("UInt32")];
+        for table in invalid {
+            match table.build() {
+                Err(Error::InvalidArgument(_)) => (),
+                _ => panic!("invalid combination should be rejected"),
+            }
+        }
+    }
+}
@@ -0,0 +1,669 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+use std::io::Read;
+use std::mem;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use serde::de::{self, SeqAccess, Visitor};
+use serde_json;
+use serde_json::Value;
+use builtin::command_query::CommandQuery;
+use builtin::load::Load;
+use client::GroongaClient;
+use error::Error;
+use load_result::LoadError;
+use retry::RetryPolicy;
+
+/// Report of a batch which is sent by `BulkLoader`.
+#[derive(Debug)]
+pub struct BatchReport {
+    offset: usize,
+    n_records: usize,
+    n_loaded: u64,
+    record_errors: Vec<LoadError>,
+    error: Option<Error>,
+    elapsed: Duration,
+}
+
+impl BatchReport {
+    /// Return index of the first record of this batch.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Return a number of records in this batch.
+    pub fn n_records(&self) -> usize {
+        self.n_records
+    }
+
+    /// Return a number of loaded records.
+    pub fn n_loaded(&self) -> u64 {
+        self.n_loaded
+    }
+
+    /// Return a number of records which are not loaded.
+    pub fn n_failed(&self) -> u64 {
+        (self.n_records as u64).saturating_sub(self.n_loaded)
+    }
+
+    /// Return errors of records which are reported by Groonga.
+    ///
+    /// `LoadError::index` is an index in this batch.
+    pub fn record_errors(&self) -> &[LoadError] {
+        &self.record_errors
+    }
+
+    /// Return the error if this batch could not be sent even with retry.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// Return time to send this batch including retry.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+/// Record which is not sent because it is not serialized into an object.
+#[derive(Debug)]
+pub struct InvalidRecord {
+    index: usize,
+    error: Error,
+}
+
+impl InvalidRecord {
+    /// Return index of the record in input.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Return the reason why the record is not sent.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+}
+
+/// Aggregated report of `BulkLoader`.
+#[derive(Debug)]
+pub struct BulkLoadReport {
+    batches: Vec<BatchReport>,
+    invalid_records: Vec<InvalidRecord>,
+    error: Option<Error>,
+    elapsed: Duration,
+}
+
+impl BulkLoadReport {
+    /// Return a number of records which are read.
+    pub fn n_records(&self) -> usize {
+        self.batches.iter().map(|batch| batch.n_records).sum::<usize>() +
+        self.invalid_records.len()
+    }
+
+    /// Return a number of loaded records.
+    pub fn n_loaded(&self) -> u64 {
+        self.batches.iter().map(|batch| batch.n_loaded).sum()
+    }
+
+    /// Return a number of records which are not loaded,
+    /// including invalid records.
+    pub fn n_failed(&self) -> u64 {
+        self.batches.iter().map(|batch| batch.n_failed()).sum::<u64>() +
+        self.invalid_records.len() as u64
+    }
+
+    /// Return reports of each batch.
+    pub fn batches(&self) -> &[BatchReport] {
+        &self.batches
+    }
+
+    /// Return records which are skipped without being sent.
+    pub fn invalid_records(&self) -> &[InvalidRecord] {
+        &self.invalid_records
+    }
+
+    /// Return the error if input could not be read to the end.
+    ///
+    /// Batches which are read before the error are sent and reported.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// Return whole time to load records.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+/// Loader which splits many records into batches of `load` command.
+///
+/// Records are read only after the previous batch is sent, so at most
+/// one batch is kept in memory.
+/// A record which is not serialized into an object is reported
+/// by `BulkLoadReport::invalid_records` and the others are still loaded.
+/// Each batch is sent with `GroongaClient::execute_stream`,
+/// that is, HTTP chunked transfer encoding for `HTTPRequest`.
+///
+/// # Examples
+///
+/// ```no_run
+/// extern crate ruroonga_client as groonga;
+/// #[macro_use]
+/// extern crate serde_derive;
+///
+/// use groonga::BulkLoader;
+///
+/// #[derive(Serialize)]
+/// struct Site {
+///     #[serde(rename = "_key")]
+///     key: String,
+/// }
+///
+/// # fn main() {
+/// let mut client = groonga::HTTPRequest::new();
+/// let sites = (0..1000000).map(|n| Site { key: format!("http://example.org/{}", n) });
+/// let report = BulkLoader::new("Sites")
+///     .max_records(10000)
+///     .load(&mut client, sites);
+/// println!("loaded: {}, failed: {}", report.n_loaded(), report.n_failed());
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BulkLoader<'a> {
+    table: Cow<'a, str>,
+    max_records: usize,
+    max_bytes: usize,
+    retry: Option<RetryPolicy>,
+}
+
+impl<'a> BulkLoader<'a> {
+    ///
+    /// Create BulkLoader struct for the specified table.
+    ///
+    /// Default values are:
+    ///
+    ///   max_records: 1000
+    ///
+    ///   max_bytes: 4 MiB
+    ///
+    pub fn new<T>(table: T) -> BulkLoader<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        BulkLoader {
+            table: table.into(),
+            max_records: 1000,
+            max_bytes: 4 * 1024 * 1024,
+            retry: None,
+        }
+    }
+
+    /// Set the maximum number of records in a batch.
+    pub fn max_records(mut self, max_records: usize) -> BulkLoader<'a> {
+        self.max_records = max_records;
+        self
+    }
+
+    /// Set the maximum size of a batch in bytes.
+    ///
+    /// A record which is larger than this size is sent as a batch.
+    pub fn max_bytes(mut self, max_bytes: usize) -> BulkLoader<'a> {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Retry failed batches with the specified policy.
+    ///
+    /// `load` is not idempotent, so specify `RetryPolicy::idempotent_only(false)`
+    /// to retry batches.
+    pub fn retry(mut self, policy: RetryPolicy) -> BulkLoader<'a> {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Load records which are serialized as JSON objects.
+    pub fn load<C, I, T>(&self, client: &mut C, records: I) -> BulkLoadReport
+        where C: GroongaClient,
+              I: IntoIterator<Item = T>,
+              T: Serialize
+    {
+        let mut run = Run::new(self, client);
+        for record in records {
+            run.push(serde_json::to_vec(&record)
+                .map_err(|err| Error::Serialize(format!("{}", err))));
+        }
+        run.finish(None)
+    }
+
+    /// Load JSON objects which are read from `reader`, such as JSON Lines.
+    ///
+    /// If input is a JSON array, its elements are loaded as records.
+    /// They are read one by one, so that the array is not kept in memory.
+    /// Reading stops at corrupted input and the error is reported
+    /// by `BulkLoadReport::error`.
+    pub fn load_reader<C, R>(&self, client: &mut C, reader: R) -> BulkLoadReport
+        where C: GroongaClient,
+              R: Read
+    {
+        let mut run = Run::new(self, client);
+        let error = read_records(reader, |record| run.push(record)).err();
+        run.finish(error)
+    }
+
+    fn send<C>(&self, client: &mut C, command: &CommandQuery, batch: Batch) -> BatchReport
+        where C: GroongaClient
+    {
+        let started = Instant::now();
+        let (offset, n_records) = (batch.offset, batch.n_records);
+        let values = batch.finish();
+        let result = {
+            let mut send = || {
+                let mut body = &values[..];
+                client.execute_stream(command, &mut body).and_then(|result| result.load_result())
+            };
+            match self.retry {
+                Some(ref policy) => policy.run(false, send),
+                None => send(),
+            }
+        };
+        let (n_loaded, record_errors, error) = match result {
+            Ok(result) => (result.n_loaded_records(), result.errors().to_vec(), None),
+            Err(err) => (0, vec![], Some(err)),
+        };
+        BatchReport {
+            offset: offset,
+            n_records: n_records,
+            n_loaded: n_loaded,
+            record_errors: record_errors,
+            error: error,
+            elapsed: started.elapsed(),
+        }
+    }
+}
+
+// Records which are pushed are split into batches and sent one by one.
+struct Run<'r, 'a: 'r, C: 'r> {
+    loader: &'r BulkLoader<'a>,
+    client: &'r mut C,
+    command: CommandQuery<'a>,
+    started: Instant,
+    batches: Vec<BatchReport>,
+    invalid_records: Vec<InvalidRecord>,
+    batch: Batch,
+    index: usize,
+}
+
+impl<'r, 'a, C> Run<'r, 'a, C>
+    where C: GroongaClient
+{
+    fn new(loader: &'r BulkLoader<'a>, client: &'r mut C) -> Run<'r, 'a, C> {
+        Run {
+            loader: loader,
+            client: client,
+            command: Load::new(loader.table.clone()).output_errors(true).command(),
+            started: Instant::now(),
+            batches: vec![],
+            invalid_records: vec![],
+            batch: Batch::new(0),
+            index: 0,
+        }
+    }
+
+    // Add a serialized record, or the reason why the record is invalid.
+    fn push(&mut self, record: Result<Vec<u8>, Error>) {
+        let index = self.index;
+        self.index += 1;
+        let record = match record {
+            Ok(ref record) if record.first() != Some(&b'{') => {
+                Err(Error::InvalidArgument("record is not object".to_owned()))
+            }
+            record => record,
+        };
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                // Send the pending batch so that each batch is a range of input.
+                self.send(index + 1);
+                self.invalid_records.push(InvalidRecord {
+                    index: index,
+                    error: err,
+                });
+                return;
+            }
+        };
+        if self.batch.n_records >= self.loader.max_records ||
+           self.batch.values.len() + record.len() + 2 > self.loader.max_bytes {
+            self.send(index);
+        }
+        self.batch.push(&record);
+    }
+
+    // Send the pending batch, if any, and start the next one from `offset`.
+    fn send(&mut self, offset: usize) {
+        let batch = mem::replace(&mut self.batch, Batch::new(offset));
+        if !batch.is_empty() {
+            let report = self.loader.send(self.client, &self.command, batch);
+            self.batches.push(report);
+        }
+    }
+
+    fn finish(mut self, error: Option<Error>) -> BulkLoadReport {
+        let offset = self.index;
+        self.send(offset);
+        BulkLoadReport {
+            batches: self.batches,
+            invalid_records: self.invalid_records,
+            error: error,
+            elapsed: self.started.elapsed(),
+        }
+    }
+}
+
+// Pass records which are read from JSON Lines, or elements of a JSON array,
+// to `f` one by one.
+fn read_records<R, F>(mut reader: R, mut f: F) -> Result<(), Error>
+    where R: Read,
+          F: FnMut(Result<Vec<u8>, Error>)
+{
+    let mut first = [0];
+    loop {
+        if try!(reader.read(&mut first)) == 0 {
+            return Ok(());
+        }
+        if !(first[0] as char).is_whitespace() {
+            break;
+        }
+    }
+    let reader = io::Cursor::new(first).chain(reader);
+    let mut push = |value: Value| {
+        f(serde_json::to_vec(&value).map_err(|err| Error::Serialize(format!("{}", err))))
+    };
+    if first[0] == b'[' {
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        return de::Deserializer::deserialize_seq(&mut deserializer, Elements(&mut push))
+            .and_then(|()| deserializer.end())
+            .map_err(|err| Error::Json(format!("{}", err)));
+    }
+    for value in serde_json::Deserializer::from_reader(reader).into_iter::<Value>() {
+        push(try!(value.map_err(|err| Error::Json(format!("{}", err)))));
+    }
+    Ok(())
+}
+
+// Visitor which passes elements of a JSON array to `f` as they are parsed.
+struct Elements<F>(F);
+
+impl<'de, F> Visitor<'de> for Elements<F>
+    where F: FnMut(Value)
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "array of records")
+    }
+
+    fn visit_seq<A>(mut self, mut seq: A) -> Result<(), A::Error>
+        where A: SeqAccess<'de>
+    {
+        while let Some(value) = try!(seq.next_element()) {
+            (self.0)(value);
+        }
+        Ok(())
+    }
+}
+
+// JSON array of records which is built incrementally.
+struct Batch {
+    offset: usize,
+    n_records: usize,
+    values: Vec<u8>,
+}
+
+impl Batch {
+    fn new(offset: usize) -> Batch {
+        Batch {
+            offset: offset,
+            n_records: 0,
+            values: vec![b'['],
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.n_records == 0
+    }
+
+    fn push(&mut self, record: &[u8]) {
+        if !self.is_empty() {
+            self.values.push(b',');
+        }
+        self.values.extend_from_slice(record);
+        self.n_records += 1;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.values.push(b']');
+        self.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use result_parser::ResultParser;
+
+    // Client which loads all records except ones whose `_key` is empty.
+    struct MockClient {
+        bodies: Vec<String>,
+        failures: usize,
+    }
+
+    impl GroongaClient for MockClient {
+        fn execute<'a>(&mut self,
+                       command: &CommandQuery<'a>,
+                       body: Option<&str>)
+                       -> Result<ResultParser, Error> {
+            assert_eq!("table=Sites&output_errors=yes&command_version=3",
+                       command.make_query());
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(Error::from(io::Error::new(io::ErrorKind::ConnectionReset, "reset")));
+            }
+            let body = body.unwrap();
+            self.bodies.push(body.to_owned());
+            let records: Vec<Value> = serde_json::from_str(body).unwrap();
+            let errors = records.iter()
+                .map(|record| if record["_key"] == "" {
+                    r#"{"return_code":-22,"message":"empty key"}"#
+                } else {
+                    r#"{"return_code":0,"message":null}"#
+                })
+                .collect::<Vec<_>>();
+            let n_loaded = errors.iter().filter(|error| error.contains(":0,")).count();
+            // Groonga reports the error of the last failed record in the header.
+            let return_code = if n_loaded < records.len() { -22 } else { 0 };
+            ResultParser::parse(&format!(r#"{{"header":{{"return_code":{},"start_time":0.0,
+                                                        "elapsed_time":0.0}},
+                                             "body":{{"n_loaded_records":{},
+                                                      "errors":[{}]}}}}"#,
+                                         return_code,
+                                         n_loaded,
+                                         errors.join(",")))
+        }
+    }
+
+    fn mock_client(failures: usize) -> MockClient {
+        MockClient {
+            bodies: vec![],
+            failures: failures,
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Site {
+        #[serde(rename = "_key")]
+        key: String,
+    }
+
+    fn sites(n: usize) -> Vec<Site> {
+        (0..n).map(|n| Site { key: format!("site{}", n) }).collect()
+    }
+
+    #[test]
+    fn split_by_record_count() {
+        let mut client = mock_client(0);
+        let report = BulkLoader::new("Sites").max_records(2).load(&mut client, sites(5));
+        assert_eq!(vec![r#"[{"_key":"site0"},{"_key":"site1"}]"#,
+                        r#"[{"_key":"site2"},{"_key":"site3"}]"#,
+                        r#"[{"_key":"site4"}]"#],
+                   client.bodies);
+        assert_eq!(5, report.n_records());
+        assert_eq!(5, report.n_loaded());
+        assert_eq!(0, report.n_failed());
+        let offsets = report.batches().iter().map(|batch| batch.offset()).collect::<Vec<_>>();
+        assert_eq!(vec![0, 2, 4], offsets);
+    }
+
+    #[test]
+    fn split_by_byte_size() {
+        let mut client = mock_client(0);
+        // Each record is 17 bytes, so a batch has two records at most.
+        let report = BulkLoader::new("Sites").max_bytes(40).load(&mut client, sites(3));
+        assert_eq!(vec![r#"[{"_key":"site0"},{"_key":"site1"}]"#, r#"[{"_key":"site2"}]"#],
+                   client.bodies);
+        assert_eq!(2, report.batches().len());
+    }
+
+    #[test]
+    fn load_from_reader() {
+        let mut client = mock_client(0);
+        let input = "{\"_key\":\"site0\"}\n{\"_key\":\"\"}\n{\"_key\":\"site2\"}\n";
+        let report = BulkLoader::new("Sites").load_reader(&mut client, input.as_bytes());
+        assert!(report.error().is_none());
+        assert_eq!(3, report.n_records());
+        assert_eq!(2, report.n_loaded());
+        assert_eq!(1, report.n_failed());
+        assert!(report.batches()[0].error().is_none());
+        let errors = report.batches()[0].record_errors();
+        assert_eq!(1, errors[0].index());
+        assert_eq!(Some("empty key"), errors[0].message());
+    }
+
+    #[test]
+    fn load_top_level_array_from_reader() {
+        let mut client = mock_client(0);
+        let input = "\n [{\"_key\":\"site0\"},{\"_key\":\"site1\"},1,{\"_key\":\"site3\"}]\n";
+        let report = BulkLoader::new("Sites")
+            .max_records(1)
+            .load_reader(&mut client, input.as_bytes());
+        assert!(report.error().is_none());
+        assert_eq!(vec![r#"[{"_key":"site0"}]"#, r#"[{"_key":"site1"}]"#, r#"[{"_key":"site3"}]"#],
+                   client.bodies);
+        assert_eq!(3, report.n_loaded());
+        assert_eq!(2, report.invalid_records()[0].index());
+    }
+
+    // Reader which fails, as if connection for input is lost.
+    struct BrokenReader;
+
+    impl Read for BrokenReader {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset"))
+        }
+    }
+
+    #[test]
+    fn send_batches_while_reading_top_level_array() {
+        let mut client = mock_client(0);
+        let input = r#"[{"_key":"site0"},{"_key":"site1"},"#.as_bytes().chain(BrokenReader);
+        let report = BulkLoader::new("Sites").max_records(1).load_reader(&mut client, input);
+        // Records before the broken part are already sent.
+        assert_eq!(vec![r#"[{"_key":"site0"}]"#, r#"[{"_key":"site1"}]"#], client.bodies);
+        assert!(report.error().is_some());
+    }
+
+    #[test]
+    fn report_data_after_top_level_array() {
+        let mut client = mock_client(0);
+        let input = r#"[{"_key":"site0"}] {"_key":"site1"}"#;
+        let report = BulkLoader::new("Sites").load_reader(&mut client, input.as_bytes());
+        assert_eq!(1, report.n_loaded());
+        match report.error() {
+            Some(&Error::Json(_)) => (),
+            _ => panic!("data after array should be reported"),
+        }
+    }
+
+    #[test]
+    fn skip_non_object_records_from_reader() {
+        let mut client = mock_client(0);
+        let input = "{\"_key\":\"site0\"}\n1\n{\"_key\":\"site2\"}\n[\"site3\"]\n\
+                     {\"_key\":\"site4\"}\n";
+        let report = BulkLoader::new("Sites").load_reader(&mut client, input.as_bytes());
+        assert_eq!(vec![r#"[{"_key":"site0"}]"#,
+                        r#"[{"_key":"site2"}]"#,
+                        r#"[{"_key":"site4"}]"#],
+                   client.bodies);
+        let offsets = report.batches().iter().map(|batch| batch.offset()).collect::<Vec<_>>();
+        assert_eq!(vec![0, 2, 4], offsets);
+        let indexes = report.invalid_records()
+            .iter()
+            .map(|record| record.index())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![1, 3], indexes);
+        match *report.invalid_records()[0].error() {
+            Error::InvalidArgument(_) => (),
+            _ => panic!("non-object record should be invalid"),
+        }
+        assert_eq!(5, report.n_records());
+        assert_eq!(3, report.n_loaded());
+        assert_eq!(2, report.n_failed());
+    }
+
+    #[test]
+    fn report_corrupted_input() {
+        let mut client = mock_client(0);
+        let input = "{\"_key\":\"site0\"}\n{\"_key\":";
+        let report = BulkLoader::new("Sites").load_reader(&mut client, input.as_bytes());
+        assert_eq!(1, report.n_loaded());
+        match report.error() {
+            Some(&Error::Json(_)) => (),
+            _ => panic!("corrupted input should be reported"),
+        }
+    }
+
+    #[test]
+    fn skip_records_which_are_not_objects() {
+        let mut client = mock_client(0);
+        let records: Vec<Value> =
+            serde_json::from_str(r#"[{"_key":"site0"},"site1",{"_key":"site2"}]"#).unwrap();
+        let report = BulkLoader::new("Sites").load(&mut client, records);
+        assert_eq!(2, report.n_loaded());
+        assert_eq!(1, report.invalid_records().len());
+        assert_eq!(1, report.invalid_records()[0].index());
+    }
+
+    #[test]
+    fn retry_failed_batch() {
+        let mut client = mock_client(1);
+        let policy = RetryPolicy::new()
+            .backoff(Duration::from_millis(1), Duration::from_millis(1))
+            .idempotent_only(false);
+        let report = BulkLoader::new("Sites").retry(policy).load(&mut client, sites(2));
+        assert_eq!(2, report.n_loaded());
+        assert!(report.batches()[0].error().is_none());
+    }
+
+    #[test]
+    fn report_failed_batch() {
+        let mut client = mock_client(1);
+        let report = BulkLoader::new("Sites")
+            .max_records(1)
+            .load(&mut client, sites(2));
+        assert_eq!(1, report.n_loaded());
+        assert_eq!(1, report.n_failed());
+        match report.batches()[0].error() {
+            Some(&Error::Io(_)) => (),
+            _ => panic!("first batch should be failed"),
+        }
+    }
+}
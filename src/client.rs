@@ -0,0 +1,48 @@
+use std::io::Read;
+use builtin::command_query::CommandQuery;
+use error::Error;
+use result_parser::ResultParser;
+
+/// Transport independent Groonga client.
+///
+/// Both `HTTPRequest` and `GQTPRequest` implement this trait.
+/// Thus, code which is written against this trait can switch protocols
+/// without touching call sites.
+///
+/// # Examples
+///
+/// ```
+/// extern crate ruroonga_client as groonga;
+///
+/// use groonga::GroongaClient;
+/// use groonga::builtin::command_query::CommandQuery;
+///
+/// fn sites<C: GroongaClient>(client: &mut C) -> Result<groonga::ResultParser, groonga::Error> {
+///     let mut command = CommandQuery::new("select");
+///     command.set_argument(vec![("table", "Sites")]);
+///     client.execute(&command, None)
+/// }
+/// # fn main() {}
+/// ```
+pub trait GroongaClient {
+    /// Send command and receive parsed response.
+    ///
+    /// `body` is used as loading data for `load` command.
+    fn execute<'a>(&mut self,
+                   command: &CommandQuery<'a>,
+                   body: Option<&str>)
+                   -> Result<ResultParser, Error>;
+
+    /// Send command with loading data which is read from `body`.
+    ///
+    /// The default implementation reads whole `body` and calls `execute`.
+    /// `HTTPRequest` sends it with chunked transfer encoding instead.
+    fn execute_stream<'a>(&mut self,
+                          command: &CommandQuery<'a>,
+                          body: &mut Read)
+                          -> Result<ResultParser, Error> {
+        let mut values = String::new();
+        try!(body.read_to_string(&mut values));
+        self.execute(command, Some(&values))
+    }
+}
@@ -0,0 +1,218 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::string::FromUtf8Error;
+use hyper::error::Error as HyperError;
+#[cfg(feature="gqtp")]
+use gqtp_request::GQTPError;
+#[cfg(feature="gqtp")]
+use result_parser::ResultParser;
+use retry::Retryable;
+use return_code::ReturnCode;
+
+/// Error type of this crate.
+#[derive(Debug)]
+pub enum Error {
+    /// Transport error, such as connection refused or reset.
+    Io(io::Error),
+    /// HTTP protocol error.
+    Http(HyperError),
+    /// Non-successful HTTP status code with its response body.
+    HttpStatus(u16, String),
+    /// Malformed protocol data, such as invalid GQTP header.
+    Protocol(String),
+    /// Connect, read, write or per-request deadline was exceeded.
+    Timeout,
+    /// Response is not valid JSON or not expected structure.
+    Json(String),
+    /// Response is not valid UTF-8.
+    Encoding(FromUtf8Error),
+    /// Record cannot be deserialized into user-defined type.
+    Deserialize(String),
+    /// Record cannot be serialized into JSON value for `load`.
+    Serialize(String),
+    /// Command has invalid arguments, such as conflicting flags.
+    InvalidArgument(String),
+    /// Groonga returned non-zero return code.
+    Groonga {
+        /// Return code of Groonga, a.k.a. `grn_rc`.
+        code: ReturnCode,
+        /// Error message which is reported by Groonga.
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref err) => write!(f, "I/O error: {}", err),
+            Error::Http(ref err) => write!(f, "HTTP error: {}", err),
+            Error::HttpStatus(status, _) => write!(f, "HTTP status error: {}", status),
+            Error::Protocol(ref message) => write!(f, "protocol error: {}", message),
+            Error::Timeout => write!(f, "request timed out"),
+            Error::Json(ref message) => write!(f, "JSON decode error: {}", message),
+            Error::Encoding(ref err) => write!(f, "encoding error: {}", err),
+            Error::Deserialize(ref message) => write!(f, "deserialize error: {}", message),
+            Error::Serialize(ref message) => write!(f, "serialize error: {}", message),
+            Error::InvalidArgument(ref message) => write!(f, "invalid argument: {}", message),
+            Error::Groonga { code, ref message } => {
+                write!(f, "Groonga error ({}): {}", code.name(), message)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Io(_) => "I/O error",
+            Error::Http(_) => "HTTP error",
+            Error::HttpStatus(..) => "HTTP status error",
+            Error::Protocol(_) => "protocol error",
+            Error::Timeout => "request timed out",
+            Error::Json(_) => "JSON decode error",
+            Error::Encoding(_) => "encoding error",
+            Error::Deserialize(_) => "deserialize error",
+            Error::Serialize(_) => "serialize error",
+            Error::InvalidArgument(_) => "invalid argument",
+            Error::Groonga { .. } => "Groonga error",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            Error::Http(ref err) => Some(err),
+            Error::Encoding(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        match err.kind() {
+            io::ErrorKind::WouldBlock |
+            io::ErrorKind::TimedOut => Error::Timeout,
+            _ => Error::Io(err),
+        }
+    }
+}
+
+impl From<HyperError> for Error {
+    fn from(err: HyperError) -> Error {
+        match err {
+            HyperError::Io(err) => Error::from(err),
+            err => Error::Http(err),
+        }
+    }
+}
+
+impl From<FromUtf8Error> for Error {
+    fn from(err: FromUtf8Error) -> Error {
+        Error::Encoding(err)
+    }
+}
+
+#[cfg(feature="gqtp")]
+impl From<GQTPError> for Error {
+    fn from(err: GQTPError) -> Error {
+        match err {
+            GQTPError::InvalidProtocol => Error::Protocol("invalid GQTP header".to_owned()),
+            GQTPError::InvalidBodySize => Error::Protocol("invalid GQTP body size".to_owned()),
+            GQTPError::ErrorResponse(status, body) => groonga_error(status, &body),
+            GQTPError::Timeout => Error::Timeout,
+            GQTPError::IO(err) => Error::from(err),
+            GQTPError::EncodingError(err) => Error::Encoding(err),
+        }
+    }
+}
+
+// Error message is in the response header of `body`, if any.
+#[cfg(feature="gqtp")]
+fn groonga_error(status: u16, body: &[u8]) -> Error {
+    let code = ReturnCode::from_code(status as i16 as i64);
+    let message = String::from_utf8(body.to_vec())
+        .ok()
+        .and_then(|body| ResultParser::parse(&body).ok())
+        .and_then(|result| result.header().ok())
+        .and_then(|header| header.error_message().map(|message| message.to_owned()));
+    Error::Groonga {
+        code: code,
+        message: message.unwrap_or_else(|| code.description().to_owned()),
+    }
+}
+
+impl Retryable for Error {
+    fn io_error_kind(&self) -> Option<io::ErrorKind> {
+        match *self {
+            Error::Io(ref err) => Some(err.kind()),
+            Error::Timeout => Some(io::ErrorKind::TimedOut),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_io_error() {
+        let err = Error::from(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"));
+        match err {
+            Error::Io(ref err) => assert_eq!(io::ErrorKind::ConnectionRefused, err.kind()),
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn from_timed_out_io_error() {
+        let err = Error::from(io::Error::new(io::ErrorKind::WouldBlock, "would block"));
+        match err {
+            Error::Timeout => (),
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn from_hyper_error() {
+        match Error::from(HyperError::Status) {
+            Error::Http(HyperError::Status) => (),
+            err => panic!("unexpected error: {:?}", err),
+        }
+        let err = HyperError::Io(io::Error::new(io::ErrorKind::TimedOut, "timed out"));
+        match Error::from(err) {
+            Error::Timeout => (),
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[cfg(feature="gqtp")]
+    #[test]
+    fn from_gqtp_error() {
+        match Error::from(GQTPError::ErrorResponse(65514, vec![])) {
+            Error::Groonga { code, .. } => assert_eq!(ReturnCode::InvalidArgument, code),
+            err => panic!("unexpected error: {:?}", err),
+        }
+        let body = b"[[-22,0.0,0.0,\"invalid table name\"]]".to_vec();
+        match Error::from(GQTPError::ErrorResponse(65514, body)) {
+            Error::Groonga { message, .. } => assert_eq!("invalid table name", message),
+            err => panic!("unexpected error: {:?}", err),
+        }
+        match Error::from(GQTPError::ErrorResponse(65514, b"corrupted".to_vec())) {
+            Error::Groonga { code, message } => assert_eq!(code.description(), message),
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn display_groonga_error() {
+        let err = Error::Groonga {
+            code: ReturnCode::SyntaxError,
+            message: "syntax error: \"(\"".to_owned(),
+        };
+        assert_eq!("Groonga error (SYNTAX_ERROR): syntax error: \"(\"",
+                   err.to_string());
+    }
+}
@@ -0,0 +1,521 @@
+use std::io;
+use std::io::prelude::*;
+use std::mem;
+use std::net::TcpStream;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+use builtin::command_query::CommandQuery;
+use builtin::load::Load;
+use client::GroongaClient;
+use error::Error;
+use gqtp_frame::{GQTPFrame, GQTPHeader, GQTP_FLAG_TAIL};
+use gqtp_request::{self, GQTPError, Session};
+use gqtp_response::GQTPResponse;
+use http_pool::{PoolConfig, PoolStats};
+use load_result::LoadResult;
+use result_parser::ResultParser;
+use retry::RetryPolicy;
+use timeout::Timeouts;
+
+/// Persistent GQTP session which sends many commands over one TCP connection.
+///
+/// The connection is opened on the first call and opened again when the
+/// server has closed it. `quit` is sent when the session is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// extern crate ruroonga_client as groonga;
+///
+/// let mut connection = groonga::GQTPRequest::new().connection();
+/// let status = connection.call("status").unwrap();
+/// let tables = connection.call("table_list").unwrap();
+/// ```
+pub struct GQTPConnection {
+    addr: String,
+    timeouts: Timeouts,
+    retry: Option<RetryPolicy>,
+    stream: Option<TcpStream>,
+    last_used: Option<Instant>,
+    requests: Arc<AtomicUsize>,
+    connections: Arc<AtomicUsize>,
+}
+
+impl GQTPConnection {
+    #[doc(hidden)]
+    pub fn new(addr: String, timeouts: Timeouts, retry: Option<RetryPolicy>) -> GQTPConnection {
+        GQTPConnection {
+            addr: addr,
+            timeouts: timeouts,
+            retry: retry,
+            stream: None,
+            last_used: None,
+            requests: Arc::new(AtomicUsize::new(0)),
+            connections: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Return whether TCP connection is currently open.
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Return how many calls reused the open connection.
+    pub fn stats(&self) -> PoolStats {
+        PoolStats::new(self.requests.load(Ordering::SeqCst),
+                       self.connections.load(Ordering::SeqCst))
+    }
+
+    /// Send request and Receive response over the persistent connection.
+    pub fn call<C>(&mut self, command: C) -> Result<String, Error>
+        where C: AsRef<str>
+    {
+        gqtp_request::call(self, command.as_ref())
+    }
+
+    /// Send `quit` and close the connection.
+    ///
+    /// The next call opens a new connection.
+    pub fn close(&mut self) {
+        if let Some(mut stream) = self.stream.take() {
+            let quit = GQTPFrame::new(GQTP_FLAG_TAIL, b"quit".to_vec());
+            let _ = quit.write_to(&mut stream);
+        }
+    }
+
+    /// Send request and pass body of each response packet to `f` as it arrives.
+    ///
+    /// Failed calls are not retried, like `GQTPRequest::call_incremental`.
+    pub fn call_incremental<C, F>(&mut self, command: C, f: F) -> Result<(), Error>
+        where C: AsRef<str>,
+              F: FnMut(&[u8])
+    {
+        gqtp_request::call_incremental(self, command.as_ref(), f)
+    }
+
+    /// Send request and receive response with its header,
+    /// like `GQTPRequest::call_response`.
+    pub fn call_response<C>(&mut self, command: C, opaque: u32) -> Result<GQTPResponse, Error>
+        where C: AsRef<str>
+    {
+        gqtp_request::call_response(self, command.as_ref(), opaque)
+    }
+
+    /// Load records of `load` and return its result, like `GQTPRequest::load`.
+    pub fn load(&mut self, load: &Load) -> Result<LoadResult, Error> {
+        gqtp_request::load(self, load)
+    }
+
+    /// Send `load` command and then values which is read from `values`,
+    /// like `GQTPRequest::load_stream`.
+    pub fn load_stream(&mut self,
+                       command: &CommandQuery,
+                       values: &mut Read)
+                       -> Result<ResultParser, Error> {
+        gqtp_request::load_stream(self, command, values)
+    }
+
+    fn is_expired(&self, config: &PoolConfig) -> bool {
+        match (self.last_used, config.get_idle_timeout()) {
+            (Some(last_used), Some(idle_timeout)) => last_used.elapsed() > idle_timeout,
+            _ => false,
+        }
+    }
+}
+
+// Calls reuse the open connection.
+impl<'c> Session for &'c mut GQTPConnection {
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        self.retry.clone()
+    }
+
+    fn send_once(&mut self,
+                 command: &str,
+                 values: Option<&mut Read>,
+                 opaque: u32,
+                 f: &mut FnMut(&GQTPHeader, &[u8]))
+                 -> Result<(), GQTPError> {
+        let started = Instant::now();
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        if self.stream.as_ref().map_or(false, |stream| !is_alive(stream)) {
+            self.stream = None;
+        }
+        let mut stream = match self.stream.take() {
+            Some(stream) => stream,
+            None => {
+                let stream = try!(self.timeouts.connect(self.addr.as_str(), started));
+                self.connections.fetch_add(1, Ordering::SeqCst);
+                stream
+            }
+        };
+        try!(gqtp_request::exchange(&mut stream,
+                                    &self.timeouts,
+                                    started,
+                                    command,
+                                    values,
+                                    opaque,
+                                    f));
+        // The whole response is read, so the connection can be reused.
+        self.stream = Some(stream);
+        self.last_used = Some(Instant::now());
+        Ok(())
+    }
+}
+
+impl Drop for GQTPConnection {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl GroongaClient for GQTPConnection {
+    /// Send command with command line form via GQTP.
+    ///
+    /// The load body is sent in packets after the command.
+    fn execute<'a>(&mut self,
+                   command: &CommandQuery<'a>,
+                   body: Option<&str>)
+                   -> Result<ResultParser, Error> {
+        let result = match body {
+            Some(values) => try!(gqtp_request::call_load(self, command, values.as_bytes())),
+            None => try!(self.call(command.to_command_line())),
+        };
+        ResultParser::parse(&result)
+    }
+
+    /// Send the load body in packets without reading it at once.
+    fn execute_stream<'a>(&mut self,
+                          command: &CommandQuery<'a>,
+                          body: &mut Read)
+                          -> Result<ResultParser, Error> {
+        self.load_stream(command, body)
+    }
+}
+
+// Idle connection closed by the server is readable with no data.
+fn is_alive(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let mut buf = [0; 1];
+    let alive = match stream.peek(&mut buf) {
+        Err(ref err) => err.kind() == io::ErrorKind::WouldBlock,
+        // Unexpected data before request also makes the connection unusable.
+        Ok(_) => false,
+    };
+    stream.set_nonblocking(false).is_ok() && alive
+}
+
+/// Thread-safe pool of persistent GQTP connections.
+///
+/// Cloned pools share idle connections.
+///
+/// # Examples
+///
+/// ```no_run
+/// extern crate ruroonga_client as groonga;
+///
+/// use std::thread;
+///
+/// let pool = groonga::GQTPRequest::new().pool(groonga::PoolConfig::new().max_idle(4));
+/// let workers = (0..4).map(|_| {
+///     let pool = pool.clone();
+///     thread::spawn(move || pool.get().call("status").unwrap())
+/// }).collect::<Vec<_>>();
+/// for worker in workers {
+///     worker.join().unwrap();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct GQTPPool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    addr: String,
+    timeouts: Timeouts,
+    retry: Option<RetryPolicy>,
+    config: PoolConfig,
+    idle: Mutex<Vec<GQTPConnection>>,
+    requests: Arc<AtomicUsize>,
+    connections: Arc<AtomicUsize>,
+}
+
+impl GQTPPool {
+    #[doc(hidden)]
+    pub fn new(addr: String,
+               timeouts: Timeouts,
+               retry: Option<RetryPolicy>,
+               config: PoolConfig)
+               -> GQTPPool {
+        GQTPPool {
+            inner: Arc::new(PoolInner {
+                addr: addr,
+                timeouts: timeouts,
+                retry: retry,
+                config: config,
+                idle: Mutex::new(vec![]),
+                requests: Arc::new(AtomicUsize::new(0)),
+                connections: Arc::new(AtomicUsize::new(0)),
+            }),
+        }
+    }
+
+    /// Take an idle connection, or a new one if there is none.
+    ///
+    /// The connection is returned to the pool when it is dropped.
+    pub fn get(&self) -> PooledGQTPConnection {
+        let mut expired = vec![];
+        let connection = {
+            let mut idle = self.inner.idle.lock().unwrap_or_else(|err| err.into_inner());
+            loop {
+                match idle.pop() {
+                    Some(connection) => {
+                        if connection.is_expired(&self.inner.config) {
+                            expired.push(connection);
+                        } else {
+                            break Some(connection);
+                        }
+                    }
+                    None => break None,
+                }
+            }
+        };
+        // Expired connections send `quit` outside of the lock.
+        drop(expired);
+        let connection = connection.unwrap_or_else(|| {
+            let mut connection = GQTPConnection::new(self.inner.addr.clone(),
+                                                     self.inner.timeouts,
+                                                     self.inner.retry.clone());
+            connection.requests = self.inner.requests.clone();
+            connection.connections = self.inner.connections.clone();
+            connection
+        });
+        PooledGQTPConnection {
+            connection: Some(connection),
+            pool: self.inner.clone(),
+        }
+    }
+
+    /// Return the number of idle connections.
+    pub fn idle_connections(&self) -> usize {
+        self.inner.idle.lock().unwrap_or_else(|err| err.into_inner()).len()
+    }
+
+    /// Return how many calls reused connections of this pool.
+    pub fn stats(&self) -> PoolStats {
+        PoolStats::new(self.inner.requests.load(Ordering::SeqCst),
+                       self.inner.connections.load(Ordering::SeqCst))
+    }
+}
+
+/// Connection taken from `GQTPPool`.
+pub struct PooledGQTPConnection {
+    connection: Option<GQTPConnection>,
+    pool: Arc<PoolInner>,
+}
+
+impl Deref for PooledGQTPConnection {
+    type Target = GQTPConnection;
+
+    fn deref(&self) -> &GQTPConnection {
+        self.connection.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledGQTPConnection {
+    fn deref_mut(&mut self) -> &mut GQTPConnection {
+        self.connection.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledGQTPConnection {
+    fn drop(&mut self) {
+        let connection = match self.connection.take() {
+            Some(connection) => connection,
+            None => return,
+        };
+        if !connection.is_connected() {
+            return;
+        }
+        let mut idle = self.pool.idle.lock().unwrap_or_else(|err| err.into_inner());
+        if idle.len() < self.pool.config.get_max_idle() {
+            idle.push(connection);
+        } else {
+            mem::drop(idle);
+            mem::drop(connection);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+    use gqtp_frame::GQTP_FLAG_MORE;
+    use gqtp_request::GQTPRequest;
+    use return_code::ReturnCode;
+
+    // Reply `true` to each command until `quit` or `limit` commands.
+    fn serve(stream: &mut TcpStream, limit: Option<usize>) -> Vec<String> {
+        let mut commands = vec![];
+        while let Ok(request) = GQTPFrame::read_from(stream) {
+            let command = String::from_utf8(request.into_body()).unwrap();
+            commands.push(command.clone());
+            if command == "quit" {
+                break;
+            }
+            GQTPFrame::new(GQTP_FLAG_TAIL, b"[[0,0.0,0.0],true]".to_vec())
+                .write_to(stream)
+                .unwrap();
+            if limit == Some(commands.len()) {
+                break;
+            }
+        }
+        commands
+    }
+
+    #[test]
+    fn reuse_connection_and_quit_on_drop() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            serve(&mut stream, None)
+        });
+        let mut connection = GQTPRequest::new().with_addr(addr.to_string()).connection();
+        assert!(!connection.is_connected());
+        for _ in 0..3 {
+            assert_eq!("[[0,0.0,0.0],true]", connection.call("status").unwrap());
+        }
+        assert!(connection.is_connected());
+        assert_eq!(PoolStats::new(3, 1), connection.stats());
+        drop(connection);
+        assert_eq!(vec!["status", "status", "status", "quit"], server.join().unwrap());
+    }
+
+    #[test]
+    fn reconnect_after_server_closed_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (closed, wait_closed) = mpsc::channel();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            serve(&mut stream, Some(1));
+            drop(stream);
+            closed.send(()).unwrap();
+            let (mut stream, _) = listener.accept().unwrap();
+            serve(&mut stream, None)
+        });
+        let mut connection = GQTPRequest::new().with_addr(addr.to_string()).connection();
+        connection.call("status").unwrap();
+        wait_closed.recv().unwrap();
+        // Give the FIN time to arrive at the client.
+        thread::sleep(::std::time::Duration::from_millis(50));
+        connection.call("table_list").unwrap();
+        assert_eq!(PoolStats::new(2, 2), connection.stats());
+        drop(connection);
+        assert_eq!(vec!["table_list", "quit"], server.join().unwrap());
+    }
+
+    #[test]
+    fn reuse_connection_after_multi_packet_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            GQTPFrame::read_from(&mut stream).unwrap();
+            let error = GQTPFrame::new(GQTP_FLAG_MORE, b"[[-22,0.0,0.0,".to_vec());
+            stream.write_all(&status_packet(error, 65514)).unwrap();
+            GQTPFrame::new(GQTP_FLAG_TAIL, b"\"invalid argument\"]]".to_vec())
+                .write_to(&mut stream)
+                .unwrap();
+            serve(&mut stream, None)
+        });
+        let mut connection = GQTPRequest::new().with_addr(addr.to_string()).connection();
+        let mut packets = 0;
+        match connection.call_incremental("select", |_| packets += 1) {
+            Err(Error::Groonga { code: ReturnCode::InvalidArgument, .. }) => (),
+            result => panic!("status error should be returned: {:?}", result),
+        }
+        assert_eq!(0, packets);
+        assert_eq!("[[0,0.0,0.0],true]", connection.call("status").unwrap());
+        assert_eq!(PoolStats::new(2, 1), connection.stats());
+        drop(connection);
+        assert_eq!(vec!["status", "quit"], server.join().unwrap());
+    }
+
+    #[test]
+    fn load_and_call_over_one_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut values = vec![];
+            loop {
+                let packet = GQTPFrame::read_from(&mut stream).unwrap();
+                values.push(String::from_utf8(packet.body().to_vec()).unwrap());
+                if packet.header().flags() & GQTP_FLAG_TAIL != 0 {
+                    break;
+                }
+            }
+            GQTPFrame::new(GQTP_FLAG_TAIL, b"[[0,0.0,0.0],1]".to_vec())
+                .write_to(&mut stream)
+                .unwrap();
+            (values, serve(&mut stream, None))
+        });
+        let mut connection = GQTPRequest::new().with_addr(addr.to_string()).connection();
+        let load = Load::new("Sites")
+            .columns(vec!["_key", "title"])
+            .unwrap()
+            .values(vec![("http://example.org/", "test")])
+            .unwrap();
+        assert_eq!(1, connection.load(&load).unwrap().n_loaded_records());
+        assert_eq!("[[0,0.0,0.0],true]", connection.call("status").unwrap());
+        drop(connection);
+        let (values, commands) = server.join().unwrap();
+        assert_eq!(vec!["load --table Sites --columns _key,title", load.body()], values);
+        assert_eq!(vec!["status", "quit"], commands);
+    }
+
+    // Encode `frame` with status code of a response.
+    fn status_packet(frame: GQTPFrame, status: u16) -> Vec<u8> {
+        let mut buf = vec![];
+        frame.write_to(&mut buf).unwrap();
+        buf[6] = (status >> 8) as u8;
+        buf[7] = status as u8;
+        buf
+    }
+
+    #[test]
+    fn share_pool_among_threads() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            thread::spawn(move || serve(&mut stream, None));
+        });
+        let pool = GQTPRequest::new()
+            .with_addr(addr.to_string())
+            .pool(PoolConfig::new().max_idle(2));
+        let workers = (0..4)
+            .map(|_| {
+                let pool = pool.clone();
+                thread::spawn(move || for _ in 0..5 {
+                    pool.get().call("status").unwrap();
+                })
+            })
+            .collect::<Vec<_>>();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        let stats = pool.stats();
+        assert_eq!(20, stats.hits() + stats.misses());
+        assert!(stats.misses() <= 4);
+        assert!(pool.idle_connections() <= 2);
+    }
+}
@@ -0,0 +1,384 @@
+use std::io::prelude::*;
+use byteorder::{BigEndian, WriteBytesExt, ReadBytesExt};
+use gqtp_request::GQTPError;
+use return_code::ReturnCode;
+
+/// Magic number of GQTP header.
+pub const GQTP_PROTOCOL: u8 = 0xc7;
+/// Size of GQTP header in bytes.
+pub const GQTP_HEADER_SIZE: usize = 24;
+/// Flag which means that more frames follow.
+pub const GQTP_FLAG_MORE: u8 = 0x01;
+/// Flag which means the last frame.
+pub const GQTP_FLAG_TAIL: u8 = 0x02;
+/// Flag which means the first frame.
+pub const GQTP_FLAG_HEAD: u8 = 0x04;
+/// Flag which requests no response.
+pub const GQTP_FLAG_QUIET: u8 = 0x08;
+/// Flag which requests closing connection.
+pub const GQTP_FLAG_QUIT: u8 = 0x10;
+
+// Body size is handled as signed 32-bit integer by Groonga.
+const MAX_BODY_SIZE: u32 = 0x7fff_ffff;
+
+/// Type of content in GQTP frame, a.k.a. `grn_content_type`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GQTPContentType {
+    /// Body without type, such as a request.
+    None,
+    /// Tab separated text.
+    Text,
+    /// JSON.
+    Json,
+    /// XML.
+    Xml,
+    /// MessagePack.
+    MessagePack,
+    /// Groonga command list.
+    GroongaCommandList,
+}
+
+impl GQTPContentType {
+    /// Convert from type of content in header.
+    pub fn from_query_type(query_type: u8) -> Option<GQTPContentType> {
+        match query_type {
+            0 => Some(GQTPContentType::None),
+            1 => Some(GQTPContentType::Text),
+            2 => Some(GQTPContentType::Json),
+            3 => Some(GQTPContentType::Xml),
+            4 => Some(GQTPContentType::MessagePack),
+            5 => Some(GQTPContentType::GroongaCommandList),
+            _ => None,
+        }
+    }
+
+    /// Return type of content in header.
+    pub fn query_type(&self) -> u8 {
+        match *self {
+            GQTPContentType::None => 0,
+            GQTPContentType::Text => 1,
+            GQTPContentType::Json => 2,
+            GQTPContentType::Xml => 3,
+            GQTPContentType::MessagePack => 4,
+            GQTPContentType::GroongaCommandList => 5,
+        }
+    }
+}
+
+/// Header of a GQTP frame.
+///
+/// See [GQTP specification](http://groonga.org/docs/spec/gqtp.html).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GQTPHeader {
+    query_type: u8,
+    key_length: u16,
+    level: u8,
+    flags: u8,
+    status: u16,
+    size: u32,
+    opaque: u32,
+    cas: u64,
+}
+
+impl GQTPHeader {
+    /// Create header of a request frame.
+    pub fn new(flags: u8, size: u32) -> GQTPHeader {
+        GQTPHeader {
+            flags: flags,
+            size: size,
+            ..GQTPHeader::default()
+        }
+    }
+
+    /// Set opaque value which is echoed back by server.
+    pub fn with_opaque(mut self, opaque: u32) -> GQTPHeader {
+        self.opaque = opaque;
+        self
+    }
+
+    /// Return type of content, such as 2 for JSON.
+    pub fn query_type(&self) -> u8 {
+        self.query_type
+    }
+
+    /// Return typed type of content.
+    pub fn content_type(&self) -> GQTPContentType {
+        GQTPContentType::from_query_type(self.query_type).unwrap_or(GQTPContentType::None)
+    }
+
+    /// Return flags such as `GQTP_FLAG_TAIL`.
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// Return status of response, a.k.a. `grn_rc` in 16-bit.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Return status as Groonga's return code.
+    pub fn return_code(&self) -> ReturnCode {
+        ReturnCode::from_code(self.status as i16 as i64)
+    }
+
+    /// Return size of body in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Return opaque value which is echoed back by server.
+    pub fn opaque(&self) -> u32 {
+        self.opaque
+    }
+
+    /// Return CAS value.
+    pub fn cas(&self) -> u64 {
+        self.cas
+    }
+
+    /// Write header in network byte order.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), GQTPError> {
+        let mut buf = Vec::with_capacity(GQTP_HEADER_SIZE);
+        try!(buf.write_u8(GQTP_PROTOCOL));
+        try!(buf.write_u8(self.query_type));
+        try!(buf.write_u16::<BigEndian>(self.key_length));
+        try!(buf.write_u8(self.level));
+        try!(buf.write_u8(self.flags));
+        try!(buf.write_u16::<BigEndian>(self.status));
+        try!(buf.write_u32::<BigEndian>(self.size));
+        try!(buf.write_u32::<BigEndian>(self.opaque));
+        try!(buf.write_u64::<BigEndian>(self.cas));
+        try!(writer.write_all(&buf));
+        Ok(())
+    }
+
+    /// Read exactly one header and validate it.
+    ///
+    /// This method returns `GQTPError::InvalidProtocol` for unexpected magic
+    /// number, type of content or flags, and `GQTPError::InvalidBodySize`
+    /// for too large body.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<GQTPHeader, GQTPError> {
+        let mut buf = [0; GQTP_HEADER_SIZE];
+        try!(reader.read_exact(&mut buf));
+        let mut buf = &buf[..];
+        let protocol = try!(buf.read_u8());
+        let header = GQTPHeader {
+            query_type: try!(buf.read_u8()),
+            key_length: try!(buf.read_u16::<BigEndian>()),
+            level: try!(buf.read_u8()),
+            flags: try!(buf.read_u8()),
+            status: try!(buf.read_u16::<BigEndian>()),
+            size: try!(buf.read_u32::<BigEndian>()),
+            opaque: try!(buf.read_u32::<BigEndian>()),
+            cas: try!(buf.read_u64::<BigEndian>()),
+        };
+        if protocol != GQTP_PROTOCOL ||
+           GQTPContentType::from_query_type(header.query_type).is_none() ||
+           header.flags & (GQTP_FLAG_MORE | GQTP_FLAG_TAIL) == 0 {
+            return Err(GQTPError::InvalidProtocol);
+        }
+        if header.size > MAX_BODY_SIZE {
+            return Err(GQTPError::InvalidBodySize);
+        }
+        Ok(header)
+    }
+}
+
+/// GQTP frame which consists of header and body.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GQTPFrame {
+    header: GQTPHeader,
+    body: Vec<u8>,
+}
+
+impl GQTPFrame {
+    /// Create request frame whose size is taken from `body`.
+    pub fn new(flags: u8, body: Vec<u8>) -> GQTPFrame {
+        GQTPFrame {
+            header: GQTPHeader::new(flags, body.len() as u32),
+            body: body,
+        }
+    }
+
+    /// Set opaque value which is echoed back by server.
+    pub fn with_opaque(mut self, opaque: u32) -> GQTPFrame {
+        self.header = self.header.with_opaque(opaque);
+        self
+    }
+
+    /// Return header.
+    pub fn header(&self) -> &GQTPHeader {
+        &self.header
+    }
+
+    /// Return body.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Consume frame and return body.
+    pub fn into_body(self) -> Vec<u8> {
+        self.body
+    }
+
+    /// Write header and body.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), GQTPError> {
+        if self.body.len() > MAX_BODY_SIZE as usize {
+            return Err(GQTPError::InvalidBodySize);
+        }
+        try!(self.header.write_to(writer));
+        try!(writer.write_all(&self.body));
+        Ok(())
+    }
+
+    /// Read exactly one frame, header then body of its size.
+    ///
+    /// Partial reads are continued until the whole frame is read, and bytes
+    /// after the frame are left in `reader`.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<GQTPFrame, GQTPError> {
+        let header = try!(GQTPHeader::read_from(reader));
+        let mut body = vec![];
+        try!(reader.take(header.size as u64).read_to_end(&mut body));
+        if body.len() != header.size as usize {
+            return Err(GQTPError::InvalidBodySize);
+        }
+        Ok(GQTPFrame {
+            header: header,
+            body: body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    // Stream which delivers only one byte for each read.
+    struct OneByteReader {
+        data: Vec<u8>,
+        position: usize,
+    }
+
+    impl OneByteReader {
+        fn new(data: Vec<u8>) -> OneByteReader {
+            OneByteReader {
+                data: data,
+                position: 0,
+            }
+        }
+    }
+
+    impl Read for OneByteReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if buf.is_empty() || self.position >= self.data.len() {
+                return Ok(0);
+            }
+            buf[0] = self.data[self.position];
+            self.position += 1;
+            Ok(1)
+        }
+    }
+
+    fn response(flags: u8, status: u16, body: &[u8]) -> Vec<u8> {
+        let mut data = vec![GQTP_PROTOCOL, 2, 0, 0, 0, flags];
+        data.write_u16::<BigEndian>(status).unwrap();
+        data.write_u32::<BigEndian>(body.len() as u32).unwrap();
+        data.write_u32::<BigEndian>(7).unwrap();
+        data.write_u64::<BigEndian>(9).unwrap();
+        data.extend_from_slice(body);
+        data
+    }
+
+    #[test]
+    fn encode_request_frame() {
+        let mut buf = vec![];
+        GQTPFrame::new(GQTP_FLAG_TAIL, b"status".to_vec()).write_to(&mut buf).unwrap();
+        assert_eq!(vec![0xc7, 0, 0, 0, 0, 0x02, 0, 0, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                        0, 0, b's', b't', b'a', b't', b'u', b's'],
+                   buf);
+    }
+
+    #[test]
+    fn decode_frame_byte_by_byte() {
+        let mut reader = OneByteReader::new(response(GQTP_FLAG_TAIL, 0, b"[[0,0.0,0.0],true]"));
+        let frame = GQTPFrame::read_from(&mut reader).unwrap();
+        assert_eq!(2, frame.header().query_type());
+        assert_eq!(GQTPContentType::Json, frame.header().content_type());
+        assert_eq!(7, frame.header().opaque());
+        assert_eq!(9, frame.header().cas());
+        assert_eq!(b"[[0,0.0,0.0],true]", frame.body());
+    }
+
+    #[test]
+    fn decode_large_frame_without_padding() {
+        let body = (0..20000).map(|n| b'a' + (n % 26) as u8).collect::<Vec<_>>();
+        let mut data = response(GQTP_FLAG_TAIL, 0, &body);
+        data.extend(response(GQTP_FLAG_TAIL, 0, b"next"));
+        let mut reader = OneByteReader::new(data);
+        assert_eq!(body, GQTPFrame::read_from(&mut reader).unwrap().into_body());
+        assert_eq!(b"next", GQTPFrame::read_from(&mut reader).unwrap().body());
+    }
+
+    #[test]
+    fn round_trip_frame() {
+        let frame = GQTPFrame::new(GQTP_FLAG_MORE | GQTP_FLAG_QUIET, b"load".to_vec());
+        let mut buf = vec![];
+        frame.write_to(&mut buf).unwrap();
+        assert_eq!(frame, GQTPFrame::read_from(&mut OneByteReader::new(buf)).unwrap());
+    }
+
+    #[test]
+    fn typed_header_fields() {
+        let frame = GQTPFrame::new(GQTP_FLAG_TAIL, vec![]).with_opaque(42);
+        let mut buf = vec![];
+        frame.write_to(&mut buf).unwrap();
+        assert_eq!(&[0, 0, 0, 42], &buf[12..16]);
+        assert_eq!(GQTPContentType::None, frame.header().content_type());
+        assert_eq!(ReturnCode::Success, frame.header().return_code());
+
+        let data = response(GQTP_FLAG_TAIL, 65514, b"");
+        let header = GQTPHeader::read_from(&mut OneByteReader::new(data)).unwrap();
+        assert_eq!(ReturnCode::InvalidArgument, header.return_code());
+        for query_type in 0..6 {
+            let content_type = GQTPContentType::from_query_type(query_type).unwrap();
+            assert_eq!(query_type, content_type.query_type());
+        }
+        assert_eq!(None, GQTPContentType::from_query_type(6));
+    }
+
+    #[test]
+    fn reject_invalid_header() {
+        let mut invalid_magic = response(GQTP_FLAG_TAIL, 0, b"");
+        invalid_magic[0] = 0xc8;
+        let mut invalid_type = response(GQTP_FLAG_TAIL, 0, b"");
+        invalid_type[1] = 6;
+        let without_flags = response(0, 0, b"");
+        for data in vec![invalid_magic, invalid_type, without_flags] {
+            match GQTPFrame::read_from(&mut OneByteReader::new(data)) {
+                Err(GQTPError::InvalidProtocol) => (),
+                result => panic!("invalid header should be rejected: {:?}", result),
+            }
+        }
+        let mut too_large = response(GQTP_FLAG_TAIL, 0, b"");
+        too_large[8] = 0x80;
+        match GQTPFrame::read_from(&mut OneByteReader::new(too_large)) {
+            Err(GQTPError::InvalidBodySize) => (),
+            result => panic!("too large body should be rejected: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn reject_truncated_frame() {
+        let mut truncated = response(GQTP_FLAG_TAIL, 0, b"[[0,0.0,0.0],true]");
+        truncated.pop();
+        match GQTPFrame::read_from(&mut OneByteReader::new(truncated)) {
+            Err(GQTPError::InvalidBodySize) => (),
+            result => panic!("truncated body should be rejected: {:?}", result),
+        }
+        match GQTPFrame::read_from(&mut OneByteReader::new(vec![GQTP_PROTOCOL, 2, 0])) {
+            Err(GQTPError::IO(ref err)) if err.kind() == io::ErrorKind::UnexpectedEof => (),
+            result => panic!("truncated header should be rejected: {:?}", result),
+        }
+    }
+}
@@ -1,26 +1,56 @@
+use std::fmt;
 use std::io;
-use std::io::Cursor;
 use std::io::prelude::*;
 use std::borrow::Cow;
 use std::net::TcpStream;
 use std::string::FromUtf8Error;
-use byteorder::{BigEndian, WriteBytesExt, ReadBytesExt};
+use std::time::{Duration, Instant};
+use builtin::command_query::CommandQuery;
+use builtin::load::Load;
+use client::GroongaClient;
+use gqtp_connection::{GQTPConnection, GQTPPool};
+use gqtp_frame::{GQTPFrame, GQTPHeader, GQTP_FLAG_MORE, GQTP_FLAG_QUIET, GQTP_FLAG_TAIL};
+use gqtp_response::GQTPResponse;
+use http_pool::PoolConfig;
+use load_result::LoadResult;
+use result_parser::ResultParser;
+use error::Error;
+use retry::{self, RetryPolicy};
+use timeout::Timeouts;
 
-const RECV_BUF_SIZE: usize = 8192;
-const GQTP_HEADER_SIZE: usize = 24;
+// Maximum size of each packet of loaded values.
+const LOAD_PACKET_SIZE: u64 = 64 * 1024;
 
 #[derive(Debug)]
 pub enum GQTPError {
     InvalidProtocol,
     InvalidBodySize,
-    StatusError(u16),
+    ErrorResponse(u16, Vec<u8>),
+    Timeout,
     IO(io::Error),
     EncodingError(FromUtf8Error),
 }
 
 impl From<io::Error> for GQTPError {
     fn from(err: io::Error) -> GQTPError {
-        GQTPError::IO(err)
+        match err.kind() {
+            io::ErrorKind::WouldBlock |
+            io::ErrorKind::TimedOut => GQTPError::Timeout,
+            _ => GQTPError::IO(err),
+        }
+    }
+}
+
+impl fmt::Display for GQTPError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GQTPError::InvalidProtocol => write!(f, "invalid GQTP protocol"),
+            GQTPError::InvalidBodySize => write!(f, "invalid GQTP body size"),
+            GQTPError::ErrorResponse(status, _) => write!(f, "GQTP status error: {}", status),
+            GQTPError::Timeout => write!(f, "GQTP request timed out"),
+            GQTPError::IO(ref err) => write!(f, "{}", err),
+            GQTPError::EncodingError(ref err) => write!(f, "{}", err),
+        }
     }
 }
 
@@ -33,11 +63,17 @@ impl From<FromUtf8Error> for GQTPError {
 /// Request [GQTP protocol](http://groonga.org/docs/spec/gqtp.html) over TcpStream
 pub struct GQTPRequest<'a> {
     addr: Cow<'a, str>,
+    timeouts: Timeouts,
+    retry: Option<RetryPolicy>,
 }
 
 impl<'a> Default for GQTPRequest<'a> {
     fn default() -> GQTPRequest<'a> {
-        GQTPRequest { addr: Cow::Borrowed("127.0.0.1:10043") }
+        GQTPRequest {
+            addr: Cow::Borrowed("127.0.0.1:10043"),
+            timeouts: Timeouts::default(),
+            retry: None,
+        }
     }
 }
 
@@ -63,78 +99,512 @@ impl<'a> GQTPRequest<'a> {
         self
     }
 
+    /// Set timeout for establishing connection.
+    pub fn connect_timeout(mut self, timeout: Duration) -> GQTPRequest<'a> {
+        self.timeouts.connect = Some(timeout);
+        self
+    }
+
+    /// Set timeout for each read operation.
+    pub fn read_timeout(mut self, timeout: Duration) -> GQTPRequest<'a> {
+        self.timeouts.read = Some(timeout);
+        self
+    }
+
+    /// Set timeout for each write operation.
+    pub fn write_timeout(mut self, timeout: Duration) -> GQTPRequest<'a> {
+        self.timeouts.write = Some(timeout);
+        self
+    }
+
+    /// Set overall deadline of each `call`.
+    ///
+    /// Timed out call returns `Error::Timeout`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate ruroonga_client as groonga;
+    ///
+    /// use std::time::Duration;
+    ///
+    /// groonga::GQTPRequest::new()
+    ///   .connect_timeout(Duration::from_secs(1))
+    ///   .read_timeout(Duration::from_secs(5))
+    ///   .deadline(Duration::from_secs(10));
+    /// ```
+    pub fn deadline(mut self, deadline: Duration) -> GQTPRequest<'a> {
+        self.timeouts.deadline = Some(deadline);
+        self
+    }
+
+    /// Retry failed calls with specified policy.
+    ///
+    /// Commands which modify database, such as `load`, are retried
+    /// only if `idempotent_only(false)` is specified.
+    pub fn retry(mut self, policy: RetryPolicy) -> GQTPRequest<'a> {
+        self.retry = Some(policy);
+        self
+    }
+
     /// Send request and Receive response.
-    pub fn call<C>(&self, command: C) -> Result<String, GQTPError>
+    pub fn call<C>(&self, command: C) -> Result<String, Error>
         where C: AsRef<str>
     {
-        // send
-        let mut stream = try!(TcpStream::connect(self.addr.as_ref()));
-        let mut send_buf = vec![];
-        try!(send_buf.write_u8(0xc7));
-        try!(send_buf.write_u8(0));
-        try!(send_buf.write_i16::<BigEndian>(0));
-        try!(send_buf.write_u8(0));
-        try!(send_buf.write_u8(0x02)); // flags
-        try!(send_buf.write_u16::<BigEndian>(0));
-        try!(send_buf.write_u32::<BigEndian>(command.as_ref().len() as u32));
-        try!(send_buf.write_u32::<BigEndian>(0));
-        try!(send_buf.write_u64::<BigEndian>(0));
-        send_buf.extend_from_slice(command.as_ref().as_bytes());
-        let _ = stream.write_all(send_buf.as_slice());
-
-        // receive and check protocol header value
-        let mut read_buf = vec![0; RECV_BUF_SIZE];
-        let _ = stream.read(&mut read_buf);
-        let mut buf = Cursor::new(read_buf);
-
-        let protocol = try!(buf.read_u8());
-        let query_type = try!(buf.read_u8());
-        if protocol != 0xc7 || query_type > 5 {
-            return Err(GQTPError::InvalidProtocol);
-        }
-        let _ = try!(buf.read_i16::<BigEndian>());
-        let _ = try!(buf.read_u8());
-
-        let flags = try!(buf.read_u8());
-        if !((flags & 0x01) == 0x01 || (flags & 0x02) == 0x02) {
-            return Err(GQTPError::InvalidProtocol);
-        }
-
-        let status = try!(buf.read_u16::<BigEndian>());
-        if status != 0 && status != 1 {
-            return Err(GQTPError::StatusError(status));
-        }
-        let size = try!(buf.read_i32::<BigEndian>()) as usize;
-        let _ = try!(buf.read_i32::<BigEndian>()); // opaque
-        let _ = try!(buf.read_i64::<BigEndian>()); // cas
-
-        // read body
-        let mut msg_buf_len = if (size + GQTP_HEADER_SIZE) > RECV_BUF_SIZE {
-            RECV_BUF_SIZE - GQTP_HEADER_SIZE
+        call(self, command.as_ref())
+    }
+
+    /// Send request and pass body of each response packet to `f` as it arrives.
+    ///
+    /// This is useful for large output such as `dump`. Failed calls are not
+    /// retried because a part of the response may have been passed already.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate ruroonga_client as groonga;
+    ///
+    /// use std::io::{self, Write};
+    ///
+    /// let stdout = io::stdout();
+    /// let mut stdout = stdout.lock();
+    /// groonga::GQTPRequest::new()
+    ///     .call_incremental("dump", |packet| stdout.write_all(packet).unwrap())
+    ///     .unwrap();
+    /// ```
+    pub fn call_incremental<C, F>(&self, command: C, f: F) -> Result<(), Error>
+        where C: AsRef<str>,
+              F: FnMut(&[u8])
+    {
+        call_incremental(self, command.as_ref(), f)
+    }
+
+    /// Send request and receive response with its header.
+    ///
+    /// `opaque` is echoed back in the response header, so that the response
+    /// can be correlated with the request. Error status of Groonga is not an
+    /// error of this method but reported by `GQTPResponse::return_code`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate ruroonga_client as groonga;
+    ///
+    /// let response = groonga::GQTPRequest::new().call_response("status", 42).unwrap();
+    /// assert_eq!(42, response.opaque());
+    /// assert!(response.return_code().is_success());
+    /// if response.content_type() == groonga::GQTPContentType::Json {
+    ///     println!("{:?}", response.parse().unwrap());
+    /// }
+    /// ```
+    pub fn call_response<C>(&self, command: C, opaque: u32) -> Result<GQTPResponse, Error>
+        where C: AsRef<str>
+    {
+        call_response(self, command.as_ref(), opaque)
+    }
+
+    /// Load records of `load` and return its result.
+    ///
+    /// Failed loads are retried only if `idempotent_only(false)` is specified.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate ruroonga_client as groonga;
+    ///
+    /// use groonga::builtin::load::Load;
+    ///
+    /// let load = Load::new("Sites")
+    ///     .columns(vec!["_key", "title"])
+    ///     .unwrap()
+    ///     .values(vec![("http://example.org/", "test")])
+    ///     .unwrap();
+    /// let result = groonga::GQTPRequest::new().load(&load).unwrap();
+    /// assert_eq!(1, result.n_loaded_records());
+    /// ```
+    pub fn load(&self, load: &Load) -> Result<LoadResult, Error> {
+        self::load(self, load)
+    }
+
+    /// Send `load` command and then values which is read from `values`.
+    ///
+    /// `command` must not have `values` argument, otherwise this method returns
+    /// `Error::InvalidArgument`. Values are sent in packets with MORE flag and
+    /// the last one with TAIL flag, so that whole values are not buffered.
+    /// Failed loads are not retried.
+    pub fn load_stream(&self,
+                       command: &CommandQuery,
+                       values: &mut Read)
+                       -> Result<ResultParser, Error> {
+        load_stream(self, command, values)
+    }
+
+    /// Create persistent connection which reuses one TCP connection.
+    ///
+    /// The connection inherits address, timeouts and retry policy.
+    pub fn connection(&self) -> GQTPConnection {
+        GQTPConnection::new(self.addr.clone().into_owned(), self.timeouts, self.retry.clone())
+    }
+
+    /// Create thread-safe pool of persistent connections.
+    pub fn pool(&self, config: PoolConfig) -> GQTPPool {
+        GQTPPool::new(self.addr.clone().into_owned(),
+                      self.timeouts,
+                      self.retry.clone(),
+                      config)
+    }
+}
+
+// Each call opens a new connection.
+impl<'r, 'a> Session for &'r GQTPRequest<'a> {
+    fn retry_policy(&self) -> Option<RetryPolicy> {
+        self.retry.clone()
+    }
+
+    fn send_once(&mut self,
+                 command: &str,
+                 values: Option<&mut Read>,
+                 opaque: u32,
+                 f: &mut FnMut(&GQTPHeader, &[u8]))
+                 -> Result<(), GQTPError> {
+        let started = Instant::now();
+        let mut stream = try!(self.timeouts.connect(self.addr.as_ref(), started));
+        exchange(&mut stream, &self.timeouts, started, command, values, opaque, f)
+    }
+}
+
+// Connection which commands are sent over, shared by `GQTPRequest`
+// and `GQTPConnection`.
+pub trait Session {
+    fn retry_policy(&self) -> Option<RetryPolicy>;
+
+    // Send command and pass header and body of each response packet to `f`.
+    fn send_once(&mut self,
+                 command: &str,
+                 values: Option<&mut Read>,
+                 opaque: u32,
+                 f: &mut FnMut(&GQTPHeader, &[u8]))
+                 -> Result<(), GQTPError>;
+}
+
+pub fn call<S: Session>(mut session: S, command: &str) -> Result<String, Error> {
+    match session.retry_policy() {
+        Some(policy) => {
+            let idempotent = retry::is_idempotent(retry::command_name(command));
+            policy.run(idempotent,
+                       || call_once(&mut session, command, None).map_err(Error::from))
+        }
+        None => Ok(try!(call_once(&mut session, command, None))),
+    }
+}
+
+pub fn call_incremental<S, F>(mut session: S, command: &str, f: F) -> Result<(), Error>
+    where S: Session,
+          F: FnMut(&[u8])
+{
+    call_incremental_once(&mut session, command, None, f).map_err(Error::from)
+}
+
+pub fn call_response<S: Session>(mut session: S,
+                                 command: &str,
+                                 opaque: u32)
+                                 -> Result<GQTPResponse, Error> {
+    match session.retry_policy() {
+        Some(policy) => {
+            let idempotent = retry::is_idempotent(retry::command_name(command));
+            policy.run(idempotent, || {
+                call_response_once(&mut session, command, opaque).map_err(Error::from)
+            })
+        }
+        None => Ok(try!(call_response_once(&mut session, command, opaque))),
+    }
+}
+
+pub fn load<S: Session>(session: S, load: &Load) -> Result<LoadResult, Error> {
+    let result = try!(call_load(session, &load.command(), load.body().as_bytes()));
+    try!(ResultParser::parse(&result)).load_result()
+}
+
+pub fn load_stream<S: Session>(mut session: S,
+                               command: &CommandQuery,
+                               values: &mut Read)
+                               -> Result<ResultParser, Error> {
+    if command.get_argument("values").is_some() {
+        return Err(Error::InvalidArgument("values must be sent in packets, not as argument"
+            .to_owned()));
+    }
+    let mut result = vec![];
+    let status = call_incremental_once(&mut session,
+                                       &command.to_command_line(),
+                                       Some(values),
+                                       |packet| result.extend_from_slice(packet));
+    let result = match status {
+        Ok(()) => try!(String::from_utf8(result)),
+        Err(err) => try!(load_error_body(err)),
+    };
+    ResultParser::parse(&result)
+}
+
+pub fn call_load<S: Session>(mut session: S,
+                             command: &CommandQuery,
+                             values: &[u8])
+                             -> Result<String, Error> {
+    let command = command.to_command_line();
+    match session.retry_policy() {
+        Some(policy) => {
+            policy.run(false, || {
+                call_once(&mut session, &command, Some(values))
+                    .or_else(load_error_body)
+                    .map_err(Error::from)
+            })
+        }
+        None => Ok(try!(call_once(&mut session, &command, Some(values)).or_else(load_error_body))),
+    }
+}
+
+// Response of `load` with failed records has error status, but its body
+// has the load result with errors of each record.
+fn load_error_body(err: GQTPError) -> Result<String, GQTPError> {
+    if let GQTPError::ErrorResponse(_, ref body) = err {
+        if let Ok(body) = String::from_utf8(body.clone()) {
+            if ResultParser::parse(&body).and_then(|result| result.load_result()).is_ok() {
+                return Ok(body);
+            }
+        }
+    }
+    Err(err)
+}
+
+fn call_once<S: Session>(session: &mut S,
+                         command: &str,
+                         values: Option<&[u8]>)
+                         -> Result<String, GQTPError> {
+    let mut body = vec![];
+    let mut values = values;
+    try!(call_incremental_once(session,
+                               command,
+                               values.as_mut().map(|values| values as &mut Read),
+                               |packet| body.extend_from_slice(packet)));
+    Ok(try!(String::from_utf8(body)))
+}
+
+fn call_incremental_once<S, F>(session: &mut S,
+                               command: &str,
+                               values: Option<&mut Read>,
+                               f: F)
+                               -> Result<(), GQTPError>
+    where S: Session,
+          F: FnMut(&[u8])
+{
+    let mut check = StatusCheck::new(f);
+    try!(session.send_once(command, values, 0, &mut |header, body| check.packet(header, body)));
+    check.finish()
+}
+
+fn call_response_once<S: Session>(session: &mut S,
+                                  command: &str,
+                                  opaque: u32)
+                                  -> Result<GQTPResponse, GQTPError> {
+    let mut response = ResponseBuilder::new();
+    try!(session.send_once(command,
+                           None,
+                           opaque,
+                           &mut |header, body| response.packet(header, body)));
+    Ok(response.finish())
+}
+
+// Send command, followed by `values` for `load`, and pass header and body
+// of each response packet to `f` until TAIL packet.
+pub fn exchange<F>(stream: &mut TcpStream,
+                   timeouts: &Timeouts,
+                   started: Instant,
+                   command: &str,
+                   values: Option<&mut Read>,
+                   opaque: u32,
+                   mut f: F)
+                   -> Result<(), GQTPError>
+    where F: FnMut(&GQTPHeader, &[u8])
+{
+    let command = command.as_bytes().to_vec();
+    match values {
+        Some(values) => try!(send_load(stream, timeouts, started, command, values, opaque)),
+        None => {
+            let request = GQTPFrame::new(GQTP_FLAG_TAIL, command).with_opaque(opaque);
+            try!(send_packet(stream, timeouts, started, request))
+        }
+    }
+
+    let mut reader = DeadlineStream {
+        stream: stream,
+        timeouts: *timeouts,
+        started: started,
+    };
+    loop {
+        let packet = try!(GQTPFrame::read_from(&mut reader));
+        f(packet.header(), packet.body());
+        if packet.header().flags() & GQTP_FLAG_TAIL != 0 {
+            return Ok(());
+        }
+    }
+}
+
+// Pass bodies of packets to `f` until a packet with error status.
+//
+// Packets after the error are still read so that the connection can be
+// reused, and the error is returned by `finish` with bodies from the error
+// packet, which has the error message of Groonga.
+struct StatusCheck<F> {
+    f: F,
+    error: Option<(u16, Vec<u8>)>,
+}
+
+impl<F> StatusCheck<F>
+    where F: FnMut(&[u8])
+{
+    fn new(f: F) -> StatusCheck<F> {
+        StatusCheck {
+            f: f,
+            error: None,
+        }
+    }
+
+    fn packet(&mut self, header: &GQTPHeader, body: &[u8]) {
+        match self.error {
+            Some((_, ref mut error_body)) => error_body.extend_from_slice(body),
+            None if !header.return_code().is_success() => {
+                self.error = Some((header.status(), body.to_vec()));
+            }
+            None => (self.f)(body),
+        }
+    }
+
+    fn finish(self) -> Result<(), GQTPError> {
+        match self.error {
+            Some((status, body)) => Err(GQTPError::ErrorResponse(status, body)),
+            None => Ok(()),
+        }
+    }
+}
+
+// Concatenate bodies of packets into `GQTPResponse`.
+struct ResponseBuilder {
+    header: Option<GQTPHeader>,
+    body: Vec<u8>,
+}
+
+impl ResponseBuilder {
+    fn new() -> ResponseBuilder {
+        ResponseBuilder {
+            header: None,
+            body: vec![],
+        }
+    }
+
+    fn packet(&mut self, header: &GQTPHeader, body: &[u8]) {
+        let has_error = self.header.map_or(false, |header| !header.return_code().is_success());
+        if !has_error {
+            self.header = Some(*header);
+        }
+        self.body.extend_from_slice(body);
+    }
+
+    fn finish(self) -> GQTPResponse {
+        GQTPResponse::new(self.header.unwrap_or_default(), self.body)
+    }
+}
+
+fn send_packet(stream: &mut TcpStream,
+               timeouts: &Timeouts,
+               started: Instant,
+               packet: GQTPFrame)
+               -> Result<(), GQTPError> {
+    try!(stream.set_write_timeout(try!(timeouts.write_timeout(started))));
+    packet.write_to(stream)
+}
+
+// Send `load` command and values in packets with MORE and QUIET flags, so
+// that the server responds only to the last packet with TAIL flag.
+fn send_load(stream: &mut TcpStream,
+             timeouts: &Timeouts,
+             started: Instant,
+             command: Vec<u8>,
+             values: &mut Read,
+             opaque: u32)
+             -> Result<(), GQTPError> {
+    let mut body = try!(read_packet(values));
+    if body.is_empty() {
+        let packet = GQTPFrame::new(GQTP_FLAG_TAIL, command).with_opaque(opaque);
+        return send_packet(stream, timeouts, started, packet);
+    }
+    let packet = GQTPFrame::new(GQTP_FLAG_MORE | GQTP_FLAG_QUIET, command).with_opaque(opaque);
+    try!(send_packet(stream, timeouts, started, packet));
+    loop {
+        let next = try!(read_packet(values));
+        let flags = if next.is_empty() {
+            GQTP_FLAG_TAIL
         } else {
-            size
+            GQTP_FLAG_MORE | GQTP_FLAG_QUIET
         };
-        let mut msg = vec![0; msg_buf_len];
-        let _ = try!(buf.read(&mut msg));
-        if (size + GQTP_HEADER_SIZE) > RECV_BUF_SIZE {
-            loop {
-                let mut read_buf = vec![0; RECV_BUF_SIZE];
-                let rsize = try!(stream.read(&mut read_buf));
-                msg.extend_from_slice(read_buf.as_ref());
-                msg_buf_len += rsize;
-                if msg_buf_len >= size {
-                    break;
-                }
-            }
+        let packet = GQTPFrame::new(flags, body).with_opaque(opaque);
+        try!(send_packet(stream, timeouts, started, packet));
+        if next.is_empty() {
+            return Ok(());
         }
+        body = next;
+    }
+}
+
+fn read_packet(values: &mut Read) -> Result<Vec<u8>, GQTPError> {
+    let mut packet = vec![];
+    try!((&mut *values).take(LOAD_PACKET_SIZE).read_to_end(&mut packet));
+    Ok(packet)
+}
 
-        Ok(try!(String::from_utf8(msg)))
+// Stream which shortens read timeout as the deadline of the call approaches.
+struct DeadlineStream<'s> {
+    stream: &'s mut TcpStream,
+    timeouts: Timeouts,
+    started: Instant,
+}
+
+impl<'s> Read for DeadlineStream<'s> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        try!(self.stream.set_read_timeout(try!(self.timeouts.read_timeout(self.started))));
+        self.stream.read(buf)
+    }
+}
+
+impl<'r> GroongaClient for GQTPRequest<'r> {
+    /// Send command with command line form via GQTP.
+    ///
+    /// The load body is sent in packets after the command.
+    fn execute<'a>(&mut self,
+                   command: &CommandQuery<'a>,
+                   body: Option<&str>)
+                   -> Result<ResultParser, Error> {
+        let result = match body {
+            Some(values) => try!(call_load(&*self, command, values.as_bytes())),
+            None => try!(self.call(command.to_command_line())),
+        };
+        ResultParser::parse(&result)
+    }
+
+    /// Send the load body in packets without reading it at once.
+    fn execute_stream<'a>(&mut self,
+                          command: &CommandQuery<'a>,
+                          body: &mut Read)
+                          -> Result<ResultParser, Error> {
+        self.load_stream(command, body)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+    use gqtp_frame::GQTPContentType;
+    use return_code::ReturnCode;
 
     #[test]
     fn smoke_gqtp() {
@@ -153,4 +623,280 @@ mod tests {
         let req = GQTPRequest::new().with_addr("127.0.0.1:20043".to_string());
         assert_eq!("127.0.0.1:20043", req.addr)
     }
+
+    #[test]
+    fn read_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).unwrap();
+            // Keep connection without response until client gives up.
+            let _ = stream.read(&mut buf);
+        });
+        let req = GQTPRequest::new()
+            .with_addr(addr.to_string())
+            .read_timeout(Duration::from_millis(100));
+        match req.call("status") {
+            Err(Error::Timeout) => (),
+            _ => panic!("request should be timed out"),
+        }
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn call_with_large_response_in_small_writes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let body = format!("[[0,0.0,0.0],\"{}\"]", "a".repeat(20000));
+        let expected = body.clone();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request = GQTPFrame::read_from(&mut stream).unwrap();
+            assert_eq!(b"status", request.body());
+            let mut response = vec![];
+            GQTPFrame::new(GQTP_FLAG_TAIL, body.into_bytes()).write_to(&mut response).unwrap();
+            for chunk in response.chunks(1000) {
+                stream.write_all(chunk).unwrap();
+                stream.flush().unwrap();
+            }
+        });
+        let req = GQTPRequest::new().with_addr(addr.to_string());
+        assert_eq!(expected, req.call("status").unwrap());
+        server.join().unwrap();
+    }
+
+    // Reply to one request with packets of `bodies`, the last one with TAIL flag.
+    fn serve_packets(listener: TcpListener, bodies: Vec<&'static str>) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            GQTPFrame::read_from(&mut stream).unwrap();
+            let n_bodies = bodies.len();
+            for (i, body) in bodies.into_iter().enumerate() {
+                let flags = if i + 1 == n_bodies {
+                    GQTP_FLAG_TAIL
+                } else {
+                    GQTP_FLAG_MORE
+                };
+                GQTPFrame::new(flags, body.as_bytes().to_vec()).write_to(&mut stream).unwrap();
+            }
+        })
+    }
+
+    #[test]
+    fn call_with_multi_packet_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = serve_packets(listener, vec!["[[0,0.0,0.0],", "[[[1]", "]]]"]);
+        let req = GQTPRequest::new().with_addr(addr.to_string());
+        assert_eq!("[[0,0.0,0.0],[[[1]]]]", req.call("select Sites").unwrap());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn call_incremental_with_multi_packet_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = serve_packets(listener, vec!["table_create", " Sites", "\n"]);
+        let req = GQTPRequest::new().with_addr(addr.to_string());
+        let mut packets = vec![];
+        req.call_incremental("dump", |packet| packets.push(packet.to_vec())).unwrap();
+        assert_eq!(vec![b"table_create".to_vec(), b" Sites".to_vec(), b"\n".to_vec()],
+                   packets);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn call_response_with_opaque_and_error_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request = GQTPFrame::read_from(&mut stream).unwrap();
+            let body = b"[[-22,0.0,0.0,\"invalid table name\"]]".to_vec();
+            let mut response = vec![];
+            GQTPFrame::new(GQTP_FLAG_TAIL, body)
+                .with_opaque(request.header().opaque())
+                .write_to(&mut response)
+                .unwrap();
+            // JSON content with status -22, a.k.a. GRN_INVALID_ARGUMENT.
+            response[1] = 2;
+            response[6] = 0xff;
+            response[7] = 0xea;
+            stream.write_all(&response).unwrap();
+        });
+        let req = GQTPRequest::new().with_addr(addr.to_string());
+        let response = req.call_response("select Nonexistent", 42).unwrap();
+        assert_eq!(42, response.opaque());
+        assert_eq!(GQTPContentType::Json, response.content_type());
+        assert_eq!(GQTP_FLAG_TAIL, response.flags());
+        assert_eq!(ReturnCode::InvalidArgument, response.return_code());
+        assert_eq!(-22, response.parse().unwrap().header().unwrap().return_code().code());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn call_with_error_status_reports_message() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            GQTPFrame::read_from(&mut stream).unwrap();
+            let body = b"[[-22,0.0,0.0,\"invalid table name: <Nonexistent>\",\
+                         [[\"grn_select\",\"proc.c\",897]]]]"
+                .to_vec();
+            let mut response = vec![];
+            GQTPFrame::new(GQTP_FLAG_TAIL, body).write_to(&mut response).unwrap();
+            response[1] = 2;
+            response[6] = 0xff;
+            response[7] = 0xea;
+            stream.write_all(&response).unwrap();
+        });
+        let req = GQTPRequest::new().with_addr(addr.to_string());
+        match req.call("select Nonexistent") {
+            Err(Error::Groonga { code, message }) => {
+                assert_eq!(ReturnCode::InvalidArgument, code);
+                assert_eq!("invalid table name: <Nonexistent>", message);
+            }
+            result => panic!("error status should be reported: {:?}", result),
+        }
+        server.join().unwrap();
+    }
+
+    // Reply to `load` with JSON content which has `status` and `body`.
+    fn serve_load_result(listener: TcpListener,
+                         status: u16,
+                         body: &'static str)
+                         -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            loop {
+                let packet = GQTPFrame::read_from(&mut stream).unwrap();
+                if packet.header().flags() & GQTP_FLAG_TAIL != 0 {
+                    break;
+                }
+            }
+            let mut response = vec![];
+            GQTPFrame::new(GQTP_FLAG_TAIL, body.as_bytes().to_vec())
+                .write_to(&mut response)
+                .unwrap();
+            response[1] = 2;
+            response[6] = (status >> 8) as u8;
+            response[7] = status as u8;
+            stream.write_all(&response).unwrap();
+        })
+    }
+
+    const LOAD_RESULT_WITH_ERRORS: &str =
+        r#"{"header":{"return_code":-22,"start_time":0.0,"elapsed_time":0.0,
+                      "error":{"message":"empty key"}},
+            "body":{"n_loaded_records":1,
+                    "errors":[{"return_code":0,"message":null},
+                              {"return_code":-22,"message":"empty key"}]}}"#;
+
+    #[test]
+    fn load_with_error_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // -22, a.k.a. GRN_INVALID_ARGUMENT.
+        let server = serve_load_result(listener, 0xffea, LOAD_RESULT_WITH_ERRORS);
+        let load = Load::new("Sites")
+            .columns(vec!["_key"])
+            .and_then(|load| load.values(vec![("a",), ("",)]))
+            .unwrap()
+            .output_errors(true);
+        let result = GQTPRequest::new().with_addr(addr.to_string()).load(&load).unwrap();
+        assert_eq!(1, result.n_loaded_records());
+        assert_eq!(1, result.errors()[0].index());
+        assert_eq!(Some("empty key"), result.errors()[0].message());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn load_stream_with_error_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = serve_load_result(listener, 0xffea, LOAD_RESULT_WITH_ERRORS);
+        let mut command = CommandQuery::new("load");
+        command.set_argument(vec![("table", "Sites"), ("output_errors", "yes")]);
+        let mut values = &br#"[{"_key":"a"},{"_key":""}]"#[..];
+        let result = GQTPRequest::new()
+            .with_addr(addr.to_string())
+            .load_stream(&command, &mut values)
+            .and_then(|result| result.load_result())
+            .unwrap();
+        assert_eq!(1, result.errors().len());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn load_error_without_load_result() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = serve_load_result(listener,
+                                       0xffea,
+                                       r#"[[-22,0.0,0.0,"invalid table name: <Nonexistent>"]]"#);
+        let load = Load::new("Nonexistent");
+        match GQTPRequest::new().with_addr(addr.to_string()).load(&load) {
+            Err(Error::Groonga { message, .. }) => {
+                assert_eq!("invalid table name: <Nonexistent>", message)
+            }
+            result => panic!("load should be failed: {:?}", result),
+        }
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn reject_values_argument_in_load_stream() {
+        let mut command = CommandQuery::new("load");
+        command.set_argument(vec![("table", "Sites"), ("values", "[]")]);
+        // Nothing is sent, so the address is never connected.
+        let req = GQTPRequest::new().with_addr("127.0.0.1:1");
+        match req.load_stream(&command, &mut &b"[]"[..]) {
+            Err(Error::InvalidArgument(_)) => (),
+            result => panic!("values argument should be rejected: {:?}", result.is_ok()),
+        }
+    }
+
+    #[test]
+    fn load_values_in_packets() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut packets = vec![];
+            loop {
+                let packet = GQTPFrame::read_from(&mut stream).unwrap();
+                let is_tail = packet.header().flags() & GQTP_FLAG_TAIL != 0;
+                packets.push(packet);
+                if is_tail {
+                    break;
+                }
+            }
+            GQTPFrame::new(GQTP_FLAG_TAIL, b"[[0,0.0,0.0],3000]".to_vec())
+                .write_to(&mut stream)
+                .unwrap();
+            packets
+        });
+        let records = (0..3000).map(|n| (format!("http://example.org/{}", n), "a".repeat(30)));
+        let load = Load::new("Sites")
+            .columns(vec!["_key", "title"])
+            .unwrap()
+            .values(records)
+            .unwrap();
+        let req = GQTPRequest::new().with_addr(addr.to_string());
+        assert_eq!(3000, req.load(&load).unwrap().n_loaded_records());
+
+        let packets = server.join().unwrap();
+        assert_eq!(b"load --table Sites --columns _key,title", packets[0].body());
+        let flags = packets.iter().map(|packet| packet.header().flags()).collect::<Vec<_>>();
+        assert_eq!(vec![GQTP_FLAG_MORE | GQTP_FLAG_QUIET,
+                        GQTP_FLAG_MORE | GQTP_FLAG_QUIET,
+                        GQTP_FLAG_MORE | GQTP_FLAG_QUIET,
+                        GQTP_FLAG_TAIL],
+                   flags);
+        let values = packets[1..].iter().flat_map(|packet| packet.body().to_vec()).collect();
+        assert_eq!(load.body(), String::from_utf8(values).unwrap());
+    }
 }
@@ -0,0 +1,107 @@
+use error::Error;
+use gqtp_frame::{GQTPContentType, GQTPHeader};
+use result_parser::ResultParser;
+use return_code::ReturnCode;
+
+/// GQTP response with its header.
+///
+/// The header is the one of the last packet, or the first packet with error
+/// status if any, and the body is concatenated bodies of all packets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GQTPResponse {
+    header: GQTPHeader,
+    body: Vec<u8>,
+}
+
+impl GQTPResponse {
+    #[doc(hidden)]
+    pub fn new(header: GQTPHeader, body: Vec<u8>) -> GQTPResponse {
+        GQTPResponse {
+            header: header,
+            body: body,
+        }
+    }
+
+    /// Return raw header.
+    pub fn header(&self) -> &GQTPHeader {
+        &self.header
+    }
+
+    /// Return type of content, such as `GQTPContentType::Json`.
+    pub fn content_type(&self) -> GQTPContentType {
+        self.header.content_type()
+    }
+
+    /// Return flags such as `GQTP_FLAG_TAIL`.
+    pub fn flags(&self) -> u8 {
+        self.header.flags()
+    }
+
+    /// Return status as Groonga's return code.
+    pub fn return_code(&self) -> ReturnCode {
+        self.header.return_code()
+    }
+
+    /// Return opaque value which is specified in the request.
+    pub fn opaque(&self) -> u32 {
+        self.header.opaque()
+    }
+
+    /// Return CAS value.
+    pub fn cas(&self) -> u64 {
+        self.header.cas()
+    }
+
+    /// Return body as bytes.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Consume response and return body.
+    pub fn into_body(self) -> Vec<u8> {
+        self.body
+    }
+
+    /// Parse JSON body.
+    ///
+    /// This method returns `Error::Protocol` for other types of content.
+    pub fn parse(&self) -> Result<ResultParser, Error> {
+        if self.content_type() != GQTPContentType::Json {
+            return Err(Error::Protocol(format!("unexpected content type: {:?}",
+                                               self.content_type())));
+        }
+        let body = try!(String::from_utf8(self.body.clone()));
+        ResultParser::parse(&body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gqtp_frame::{GQTPFrame, GQTP_FLAG_TAIL};
+
+    fn response(query_type: u8, body: &[u8]) -> GQTPResponse {
+        let mut buf = vec![];
+        GQTPFrame::new(GQTP_FLAG_TAIL, body.to_vec()).with_opaque(3).write_to(&mut buf).unwrap();
+        buf[1] = query_type;
+        let frame = GQTPFrame::read_from(&mut &buf[..]).unwrap();
+        GQTPResponse::new(*frame.header(), frame.into_body())
+    }
+
+    #[test]
+    fn parse_json_response() {
+        let response = response(2, b"[[0,0.0,0.0],true]");
+        assert_eq!(GQTPContentType::Json, response.content_type());
+        assert_eq!(ReturnCode::Success, response.return_code());
+        assert_eq!(3, response.opaque());
+        assert!(response.parse().is_ok());
+    }
+
+    #[test]
+    fn reject_parsing_non_json_response() {
+        match response(1, b"true").parse() {
+            Err(Error::Protocol(_)) => (),
+            result => panic!("text response should not be parsed: {:?}", result.is_ok()),
+        }
+    }
+}
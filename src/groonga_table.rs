@@ -0,0 +1,91 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use builtin::column_create::ColumnCreate;
+use builtin::load::Load;
+use builtin::select::Select;
+use builtin::table_create::TableCreate;
+use error::Error;
+use schema::Schema;
+
+/// Rust type which is mapped to a Groonga table.
+///
+/// This trait is usually implemented by `#[derive(GroongaTable)]` in
+/// `ruroonga_client_derive` crate. Records are loaded and selected by serde,
+/// so the struct also derives `Serialize` and `Deserialize`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate ruroonga_client;
+/// #[macro_use]
+/// extern crate serde_derive;
+///
+/// use ruroonga_client::GroongaTable;
+/// use ruroonga_client::builtin::column_create::{ColumnCreate, ColumnType};
+/// use ruroonga_client::builtin::table_create::{TableCreate, TableType};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Site {
+///     #[serde(rename = "_key")]
+///     key: String,
+///     title: String,
+/// }
+///
+/// impl GroongaTable for Site {
+///     fn table_name() -> &'static str {
+///         "Sites"
+///     }
+///
+///     fn table_create() -> TableCreate<'static> {
+///         TableCreate::new("Sites", TableType::HashKey).key_type("ShortText")
+///     }
+///
+///     fn column_creates() -> Vec<ColumnCreate<'static>> {
+///         vec![ColumnCreate::new("Sites", "title", ColumnType::Scalar, "ShortText")]
+///     }
+///
+///     fn column_names() -> Vec<&'static str> {
+///         vec!["_key", "title"]
+///     }
+/// }
+///
+/// # fn main() {
+/// assert_eq!("/d/select?table=Sites&output_columns=_key%2Ctitle",
+///            Site::select().build().encode());
+/// # }
+/// ```
+pub trait GroongaTable: Serialize + DeserializeOwned {
+    /// Return table name.
+    fn table_name() -> &'static str;
+
+    /// Return `table_create` command builder of this table.
+    fn table_create() -> TableCreate<'static>;
+
+    /// Return `column_create` command builders of columns and indexes.
+    fn column_creates() -> Vec<ColumnCreate<'static>>;
+
+    /// Return column names in the order of fields, such as `_key`.
+    fn column_names() -> Vec<&'static str>;
+
+    /// Append this table, its columns and indexes to `schema`.
+    fn declare<'a>(schema: Schema<'a>) -> Schema<'a> {
+        Self::column_creates()
+            .into_iter()
+            .fold(schema.table(Self::table_create()),
+                  |schema, column| schema.column(column))
+    }
+
+    /// Create `load` command builder which loads `records` into this table.
+    fn load<I>(records: I) -> Result<Load<'static>, Error>
+        where I: IntoIterator<Item = Self>
+    {
+        Load::new(Self::table_name()).values(records)
+    }
+
+    /// Create `select` command builder which outputs columns of this type.
+    ///
+    /// Use `ResultParser::deserialize` to map records into this type.
+    fn select() -> Select<'static> {
+        Select::new(Self::table_name()).output_columns(Self::column_names())
+    }
+}
@@ -0,0 +1,271 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde_json::Value;
+use error::Error;
+
+/// Geodetic datum of geo point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GeoDatum {
+    /// `TokyoGeoPoint`
+    Tokyo,
+    /// `WGS84GeoPoint`
+    WGS84,
+}
+
+/// Geo point which is represented in milliseconds like Groonga.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GeoPoint {
+    datum: GeoDatum,
+    latitude: i32,
+    longitude: i32,
+}
+
+impl GeoPoint {
+    /// Create geo point from latitude and longitude in milliseconds.
+    pub fn new(datum: GeoDatum, latitude: i32, longitude: i32) -> GeoPoint {
+        GeoPoint {
+            datum: datum,
+            latitude: latitude,
+            longitude: longitude,
+        }
+    }
+
+    /// Return geodetic datum.
+    pub fn datum(&self) -> GeoDatum {
+        self.datum
+    }
+
+    /// Return latitude in milliseconds.
+    pub fn latitude_in_msec(&self) -> i32 {
+        self.latitude
+    }
+
+    /// Return longitude in milliseconds.
+    pub fn longitude_in_msec(&self) -> i32 {
+        self.longitude
+    }
+
+    /// Return latitude in degrees.
+    pub fn latitude(&self) -> f64 {
+        msec_to_degree(self.latitude)
+    }
+
+    /// Return longitude in degrees.
+    pub fn longitude(&self) -> f64 {
+        msec_to_degree(self.longitude)
+    }
+
+    /// Return `(latitude, longitude)` pair in degrees.
+    pub fn to_degrees(&self) -> (f64, f64) {
+        (self.latitude(), self.longitude())
+    }
+
+    // Groonga returns geo point as `"latitudexlongitude"` in milliseconds,
+    // but degrees such as `"35.6813x139.7660"` are also accepted as input.
+    fn parse(datum: GeoDatum, text: &str) -> Option<GeoPoint> {
+        let mut parts = text.splitn(2, 'x');
+        let latitude = parts.next().and_then(parse_geo_coordinate);
+        let longitude = parts.next().and_then(parse_geo_coordinate);
+        match (latitude, longitude) {
+            (Some(latitude), Some(longitude)) => Some(GeoPoint::new(datum, latitude, longitude)),
+            _ => None,
+        }
+    }
+}
+
+fn msec_to_degree(msec: i32) -> f64 {
+    msec as f64 / (60.0 * 60.0 * 1000.0)
+}
+
+fn parse_geo_coordinate(text: &str) -> Option<i32> {
+    let text = text.trim();
+    if text.contains('.') {
+        text.parse::<f64>().ok().map(|degree| (degree * 60.0 * 60.0 * 1000.0).round() as i32)
+    } else {
+        text.parse::<i32>().ok()
+    }
+}
+
+/// Value of Groonga's column which is converted by column type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GroongaValue {
+    /// `null` value.
+    Null,
+    /// `Bool`
+    Bool(bool),
+    /// `Int8`, `Int16`, `Int32` and `Int64`
+    Int(i64),
+    /// `UInt8`, `UInt16`, `UInt32` and `UInt64`
+    UInt(u64),
+    /// `Float` and `Float32`
+    Float(f64),
+    /// `Time`
+    Time(SystemTime),
+    /// `ShortText`, `Text` and `LongText`
+    Text(String),
+    /// `TokyoGeoPoint` and `WGS84GeoPoint`
+    GeoPoint(GeoPoint),
+    /// Reference to a record in the other table by its key.
+    Reference {
+        /// Name of referenced table.
+        table: String,
+        /// Key of referenced record.
+        key: Box<GroongaValue>,
+    },
+    /// Vector column.
+    Vector(Vec<GroongaValue>),
+}
+
+impl GroongaValue {
+    #[doc(hidden)]
+    pub fn from_json(value_type: &str, value: &Value) -> Result<GroongaValue, Error> {
+        match *value {
+            Value::Null => Ok(GroongaValue::Null),
+            Value::Array(ref values) => {
+                let values = try!(values.iter()
+                    .map(|value| GroongaValue::from_json(value_type, value))
+                    .collect());
+                Ok(GroongaValue::Vector(values))
+            }
+            _ => GroongaValue::from_scalar(value_type, value),
+        }
+    }
+
+    fn from_scalar(value_type: &str, value: &Value) -> Result<GroongaValue, Error> {
+        let converted = match value_type {
+            "Bool" => value.as_bool().map(GroongaValue::Bool),
+            "Int8" => to_int(value, i8::min_value() as i64, i8::max_value() as i64),
+            "Int16" => to_int(value, i16::min_value() as i64, i16::max_value() as i64),
+            "Int32" => to_int(value, i32::min_value() as i64, i32::max_value() as i64),
+            "Int64" => value.as_i64().map(GroongaValue::Int),
+            "UInt8" => to_uint(value, u8::max_value() as u64),
+            "UInt16" => to_uint(value, u16::max_value() as u64),
+            "UInt32" => to_uint(value, u32::max_value() as u64),
+            "UInt64" => value.as_u64().map(GroongaValue::UInt),
+            "Float" | "Float32" => value.as_f64().map(GroongaValue::Float),
+            "Time" => value.as_f64().and_then(to_system_time).map(GroongaValue::Time),
+            "ShortText" | "Text" | "LongText" => {
+                value.as_str().map(|text| GroongaValue::Text(text.to_owned()))
+            }
+            "TokyoGeoPoint" => {
+                value.as_str()
+                    .and_then(|text| GeoPoint::parse(GeoDatum::Tokyo, text))
+                    .map(GroongaValue::GeoPoint)
+            }
+            "WGS84GeoPoint" => {
+                value.as_str()
+                    .and_then(|text| GeoPoint::parse(GeoDatum::WGS84, text))
+                    .map(GroongaValue::GeoPoint)
+            }
+            table => {
+                infer_key(value).map(|key| {
+                    GroongaValue::Reference {
+                        table: table.to_owned(),
+                        key: Box::new(key),
+                    }
+                })
+            }
+        };
+        converted.ok_or_else(|| Error::Json(format!("{} is not a value of {}", value, value_type)))
+    }
+}
+
+fn to_int(value: &Value, min: i64, max: i64) -> Option<GroongaValue> {
+    value.as_i64()
+        .and_then(|v| if min <= v && v <= max { Some(GroongaValue::Int(v)) } else { None })
+}
+
+fn to_uint(value: &Value, max: u64) -> Option<GroongaValue> {
+    value.as_u64().and_then(|v| if v <= max { Some(GroongaValue::UInt(v)) } else { None })
+}
+
+// Groonga's time has microseconds precision.
+fn to_system_time(seconds: f64) -> Option<SystemTime> {
+    if !seconds.is_finite() {
+        return None;
+    }
+    let usec = (seconds * 1_000_000.0).round() as i64;
+    let duration = |usec: u64| Duration::new(usec / 1_000_000, (usec % 1_000_000) as u32 * 1000);
+    if usec >= 0 {
+        Some(UNIX_EPOCH + duration(usec as u64))
+    } else {
+        Some(UNIX_EPOCH - duration(-usec as u64))
+    }
+}
+
+// Key type of referenced table is not announced in column definitions.
+fn infer_key(value: &Value) -> Option<GroongaValue> {
+    match *value {
+        Value::Bool(v) => Some(GroongaValue::Bool(v)),
+        Value::Number(ref number) => {
+            number.as_u64()
+                .map(GroongaValue::UInt)
+                .or_else(|| number.as_i64().map(GroongaValue::Int))
+                .or_else(|| number.as_f64().map(GroongaValue::Float))
+        }
+        Value::String(ref text) => Some(GroongaValue::Text(text.clone())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn convert(value_type: &str, json: &str) -> Result<GroongaValue, Error> {
+        GroongaValue::from_json(value_type, &serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn convert_numbers() {
+        assert_eq!(GroongaValue::UInt(1), convert("UInt32", "1").unwrap());
+        assert_eq!(GroongaValue::UInt(18446744073709551615),
+                   convert("UInt64", "18446744073709551615").unwrap());
+        assert_eq!(GroongaValue::Int(-128), convert("Int8", "-128").unwrap());
+        assert_eq!(GroongaValue::Float(1.5), convert("Float", "1.5").unwrap());
+        assert_eq!(GroongaValue::Bool(true), convert("Bool", "true").unwrap());
+        assert!(convert("Int8", "128").is_err());
+        assert!(convert("UInt32", "-1").is_err());
+        assert!(convert("UInt32", "\"1\"").is_err());
+    }
+
+    #[test]
+    fn convert_time() {
+        let expected = UNIX_EPOCH + Duration::new(1452348610, 392810000);
+        assert_eq!(GroongaValue::Time(expected),
+                   convert("Time", "1452348610.39281").unwrap());
+        let before_epoch = UNIX_EPOCH - Duration::new(1, 500000000);
+        assert_eq!(GroongaValue::Time(before_epoch), convert("Time", "-1.5").unwrap());
+    }
+
+    #[test]
+    fn convert_geo_point() {
+        match convert("WGS84GeoPoint", "\"128452975x503157902\"").unwrap() {
+            GroongaValue::GeoPoint(point) => {
+                assert_eq!(GeoDatum::WGS84, point.datum());
+                assert_eq!(128452975, point.latitude_in_msec());
+                assert_eq!(503157902, point.longitude_in_msec());
+                let (latitude, longitude) = point.to_degrees();
+                assert!((latitude - 35.681382).abs() < 0.000001);
+                assert!((longitude - 139.766084).abs() < 0.000001);
+            }
+            value => panic!("unexpected value: {:?}", value),
+        }
+        assert_eq!(GroongaValue::GeoPoint(GeoPoint::new(GeoDatum::Tokyo, 128452975, 503157902)),
+                   convert("TokyoGeoPoint", "\"35.681382x139.766084\"").unwrap());
+        assert!(convert("TokyoGeoPoint", "\"35.681382\"").is_err());
+    }
+
+    #[test]
+    fn convert_vector_and_reference() {
+        assert_eq!(GroongaValue::Vector(vec![GroongaValue::Text("groonga".to_owned()),
+                                             GroongaValue::Text("rust".to_owned())]),
+                   convert("ShortText", r#"["groonga","rust"]"#).unwrap());
+        assert_eq!(GroongaValue::Reference {
+                       table: "Users".to_owned(),
+                       key: Box::new(GroongaValue::Text("alice".to_owned())),
+                   },
+                   convert("Users", "\"alice\"").unwrap());
+        assert_eq!(GroongaValue::Null, convert("ShortText", "null").unwrap());
+    }
+}
@@ -0,0 +1,259 @@
+use std::io;
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use hyper::net::{HttpStream, NetworkConnector, NetworkStream};
+use timeout::{RequestClock, Timeouts};
+
+/// Keep-alive connection pool settings for `HTTPRequest` and `GQTPPool`.
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    max_idle: usize,
+    idle_timeout: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> PoolConfig {
+        PoolConfig {
+            max_idle: 5,
+            idle_timeout: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl PoolConfig {
+    ///
+    /// Create PoolConfig struct.
+    ///
+    /// Default values are:
+    ///
+    ///   max_idle: 5
+    ///
+    ///   idle_timeout: 30 seconds
+    ///
+    pub fn new() -> PoolConfig {
+        PoolConfig::default()
+    }
+
+    /// Set the maximum number of idle connections *per host*.
+    pub fn max_idle(mut self, max_idle: usize) -> PoolConfig {
+        self.max_idle = max_idle;
+        self
+    }
+
+    /// Set how long an idle connection is still reused.
+    ///
+    /// `None` keeps idle connections until the server closes them.
+    pub fn idle_timeout(mut self, idle_timeout: Option<Duration>) -> PoolConfig {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Get the maximum number of idle connections per host.
+    pub fn get_max_idle(&self) -> usize {
+        self.max_idle
+    }
+
+    /// Get idle connection timeout.
+    pub fn get_idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+}
+
+/// Statistics of keep-alive connection pool.
+///
+/// A hit means that a request reused an idle connection
+/// and a miss means that a request opened a new connection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    hits: usize,
+    misses: usize,
+}
+
+impl PoolStats {
+    #[doc(hidden)]
+    pub fn new(requests: usize, connections: usize) -> PoolStats {
+        PoolStats {
+            hits: requests.saturating_sub(connections),
+            misses: connections,
+        }
+    }
+
+    /// Return the number of requests which reused an idle connection.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Return the number of requests which opened a new connection.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+}
+
+// Count connections which are actually opened by the wrapped connector.
+// It is placed under the hyper's pool, so that reused connections are not counted.
+pub struct CountingConnector<C> {
+    connector: C,
+    connections: Arc<AtomicUsize>,
+}
+
+impl<C> CountingConnector<C> {
+    pub fn new(connector: C, connections: Arc<AtomicUsize>) -> CountingConnector<C> {
+        CountingConnector {
+            connector: connector,
+            connections: connections,
+        }
+    }
+}
+
+impl<C: NetworkConnector> NetworkConnector for CountingConnector<C> {
+    type Stream = C::Stream;
+
+    fn connect(&self, host: &str, port: u16, scheme: &str) -> ::hyper::Result<C::Stream> {
+        self.connections.fetch_add(1, Ordering::SeqCst);
+        self.connector.connect(host, port, scheme)
+    }
+}
+
+// Plain HTTP connector which respects connect/read/write timeouts.
+// Connecting is also bounded by the deadline of the current request.
+pub struct TimeoutConnector {
+    timeouts: Timeouts,
+    clock: RequestClock,
+}
+
+impl TimeoutConnector {
+    pub fn new(timeouts: Timeouts, clock: RequestClock) -> TimeoutConnector {
+        TimeoutConnector {
+            timeouts: timeouts,
+            clock: clock,
+        }
+    }
+}
+
+impl NetworkConnector for TimeoutConnector {
+    type Stream = HttpStream;
+
+    fn connect(&self, host: &str, port: u16, scheme: &str) -> ::hyper::Result<HttpStream> {
+        match scheme {
+            "http" => {
+                Ok(HttpStream(try!(self.timeouts.connect((host, port), self.clock.started()))))
+            }
+            _ => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid scheme for Http")
+                    .into())
+            }
+        }
+    }
+}
+
+// Wrap streams of the connector into `DeadlineStream`.
+pub struct DeadlineConnector<C> {
+    connector: C,
+    timeouts: Timeouts,
+    clock: RequestClock,
+}
+
+impl<C> DeadlineConnector<C> {
+    pub fn new(connector: C, timeouts: Timeouts, clock: RequestClock) -> DeadlineConnector<C> {
+        DeadlineConnector {
+            connector: connector,
+            timeouts: timeouts,
+            clock: clock,
+        }
+    }
+}
+
+impl<C> NetworkConnector for DeadlineConnector<C>
+    where C: NetworkConnector,
+          C::Stream: NetworkStream + Send
+{
+    type Stream = DeadlineStream<C::Stream>;
+
+    fn connect(&self,
+               host: &str,
+               port: u16,
+               scheme: &str)
+               -> ::hyper::Result<DeadlineStream<C::Stream>> {
+        Ok(DeadlineStream {
+            stream: try!(self.connector.connect(host, port, scheme)),
+            timeouts: self.timeouts,
+            clock: self.clock.clone(),
+        })
+    }
+}
+
+// Stream which shortens read/write timeouts before each operation as the
+// deadline of the current request approaches, so that a server which sends
+// response slowly cannot extend the request over the deadline.
+pub struct DeadlineStream<S> {
+    stream: S,
+    timeouts: Timeouts,
+    clock: RequestClock,
+}
+
+impl<S: NetworkStream> Read for DeadlineStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let timeout = try!(self.timeouts.read_timeout(self.clock.started()));
+        try!(self.stream.set_read_timeout(timeout));
+        self.stream.read(buf)
+    }
+}
+
+impl<S: NetworkStream> Write for DeadlineStream<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let timeout = try!(self.timeouts.write_timeout(self.clock.started()));
+        try!(self.stream.set_write_timeout(timeout));
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl<S: NetworkStream> NetworkStream for DeadlineStream<S> {
+    fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(dur)
+    }
+
+    fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.stream.set_write_timeout(dur)
+    }
+
+    fn close(&mut self, how: Shutdown) -> io::Result<()> {
+        self.stream.close(how)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_pool_config() {
+        let config = PoolConfig::new();
+        assert_eq!(5, config.get_max_idle());
+        assert_eq!(Some(Duration::from_secs(30)), config.get_idle_timeout());
+    }
+
+    #[test]
+    fn pool_config_with_builder() {
+        let config = PoolConfig::new().max_idle(10).idle_timeout(None);
+        assert_eq!(10, config.get_max_idle());
+        assert_eq!(None, config.get_idle_timeout());
+    }
+
+    #[test]
+    fn stats_from_counts() {
+        let stats = PoolStats::new(5, 2);
+        assert_eq!(3, stats.hits());
+        assert_eq!(2, stats.misses());
+    }
+}
@@ -1,35 +1,137 @@
 use std::io;
 use std::io::Read;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use hyper::Client;
+use hyper::client::Body;
+use hyper::client::pool::{Config, Pool};
 use hyper::client::response::Response;
-use hyper::error::Error as HyperError;
-use hyper::header::{Connection, ContentType, ContentLength, Headers, Authorization, Basic};
+use hyper::header::{Connection, ContentType, Headers, Authorization, Basic};
+use hyper::net::{NetworkConnector, NetworkStream};
+#[cfg(feature="ssl")]
+use hyper::net::HttpsConnector;
 use std::option::Option;
+use builtin::command_query::CommandQuery;
+use client::GroongaClient;
+use error::Error;
+use http_pool::{CountingConnector, DeadlineConnector, PoolConfig, PoolStats, TimeoutConnector};
+#[cfg(feature="ssl")]
+use http_tls::{TlsClient, TlsConfig};
+use request_uri::RequestURI;
+use result_parser::ResultParser;
+use retry::{self, RetryPolicy};
+use timeout::{RequestClock, Timeouts};
+use uri_base::URIBase;
 
 pub struct HTTPRequest {
     client: Client,
+    uri_base: String,
     user: String,
     password: Option<String>,
     auth: bool,
+    pool: Option<PoolCounter>,
+    #[cfg(feature="ssl")]
+    tls: Option<TlsClient>,
+    timeouts: Timeouts,
+    started: Option<Instant>,
+    clock: RequestClock,
+    retry: Option<RetryPolicy>,
+}
+
+struct PoolCounter {
+    config: PoolConfig,
+    requests: usize,
+    connections: Arc<AtomicUsize>,
 }
 
 impl Default for HTTPRequest {
     fn default() -> HTTPRequest {
         HTTPRequest {
             client: Client::new(),
+            uri_base: URIBase::new().build(),
             user: "".to_string(),
             password: None,
             auth: false,
+            pool: None,
+            #[cfg(feature="ssl")]
+            tls: None,
+            timeouts: Timeouts::default(),
+            started: None,
+            clock: RequestClock::default(),
+            retry: None,
         }
     }
 }
 
+fn pooled_client<C, S>(connector: C, config: Config, idle_timeout: Option<Duration>) -> Client
+    where C: NetworkConnector<Stream = S> + Send + Sync + 'static,
+          S: NetworkStream + Send
+{
+    let mut pool = Pool::with_connector(config, connector);
+    pool.set_idle_timeout(idle_timeout);
+    Client::with_connector(pool)
+}
+
 impl HTTPRequest {
     /// Create a HTTP client.
     pub fn new() -> HTTPRequest {
         HTTPRequest::default()
     }
 
+    // Rebuild hyper client with current connection settings.
+    fn build_client(&mut self) {
+        let (config, idle_timeout, connections) = match self.pool {
+            Some(ref pool) => {
+                (Config { max_idle: pool.config.get_max_idle() },
+                 pool.config.get_idle_timeout(),
+                 pool.connections.clone())
+            }
+            None => (Config::default(), None, Arc::new(AtomicUsize::new(0))),
+        };
+        let (timeouts, clock) = (self.timeouts, self.clock.clone());
+        let connector = TimeoutConnector::new(timeouts, clock.clone());
+        let mut client = match self.tls_connector() {
+            Some(connector) => {
+                let connector = DeadlineConnector::new(connector, timeouts, clock.clone());
+                pooled_client(CountingConnector::new(connector, connections),
+                              config,
+                              idle_timeout)
+            }
+            None => {
+                let connector = DeadlineConnector::new(connector, timeouts, clock.clone());
+                pooled_client(CountingConnector::new(connector, connections),
+                              config,
+                              idle_timeout)
+            }
+        };
+        client.set_read_timeout(self.timeouts.read);
+        client.set_write_timeout(self.timeouts.write);
+        self.client = client;
+    }
+
+    #[cfg(feature="ssl")]
+    fn tls_connector(&self) -> Option<HttpsConnector<TlsClient, TimeoutConnector>> {
+        self.tls.as_ref().map(|tls| {
+            HttpsConnector::with_connector(tls.clone(),
+                                           TimeoutConnector::new(self.timeouts, self.clock.clone()))
+        })
+    }
+
+    #[cfg(not(feature="ssl"))]
+    fn tls_connector(&self) -> Option<TimeoutConnector> {
+        None
+    }
+
+    // Prepare timeouts for a new request.
+    fn start_request(&mut self) -> io::Result<()> {
+        let started = self.clock.start();
+        self.client.set_read_timeout(try!(self.timeouts.read_timeout(started)));
+        self.client.set_write_timeout(try!(self.timeouts.write_timeout(started)));
+        self.started = Some(started);
+        Ok(())
+    }
+
     /// Set authentication information.
     ///
     /// Note that this method also sets `auth: true` to use basic authentication.
@@ -53,8 +155,154 @@ impl HTTPRequest {
         self
     }
 
+    /// Use persistent keep-alive connections.
+    ///
+    /// By default, `HTTPRequest` sends `Connection: close` header and
+    /// opens a new connection for each request.
+    /// With this method, idle connections are pooled and reused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate ruroonga_client as groonga;
+    ///
+    /// use std::time::Duration;
+    /// use groonga::PoolConfig;
+    ///
+    /// let config = PoolConfig::new()
+    ///   .max_idle(10)
+    ///   .idle_timeout(Some(Duration::from_secs(60)));
+    /// groonga::HTTPRequest::new()
+    ///   .keep_alive(config);
+    /// ```
+    pub fn keep_alive(mut self, config: PoolConfig) -> HTTPRequest {
+        self.pool = Some(PoolCounter {
+            config: config,
+            requests: 0,
+            connections: Arc::new(AtomicUsize::new(0)),
+        });
+        self.build_client();
+        self
+    }
+
+    /// Use TLS settings for `https` scheme.
+    ///
+    /// Note that `URIBase` should also be specified `https` scheme.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate ruroonga_client as groonga;
+    ///
+    /// use groonga::TlsConfig;
+    ///
+    /// let uri_base = groonga::URIBase::new().scheme("https").build();
+    /// groonga::HTTPRequest::new()
+    ///   .with_uri_base(uri_base)
+    ///   .tls(TlsConfig::new().ca_file("/etc/ssl/certs/groonga-ca.pem"));
+    /// ```
+    #[cfg(feature="ssl")]
+    pub fn tls(mut self, config: TlsConfig) -> Result<HTTPRequest, Error> {
+        self.tls = Some(try!(config.build()));
+        self.build_client();
+        Ok(self)
+    }
+
+    /// Return statistics of keep-alive connection pool.
+    ///
+    /// If `keep_alive` is not specified, this method returns `None`.
+    pub fn pool_stats(&self) -> Option<PoolStats> {
+        self.pool
+            .as_ref()
+            .map(|pool| PoolStats::new(pool.requests, pool.connections.load(Ordering::SeqCst)))
+    }
+
+    /// Set timeout for establishing connection.
+    pub fn connect_timeout(mut self, timeout: Duration) -> HTTPRequest {
+        self.timeouts.connect = Some(timeout);
+        self.build_client();
+        self
+    }
+
+    /// Set timeout for each read operation.
+    pub fn read_timeout(mut self, timeout: Duration) -> HTTPRequest {
+        self.timeouts.read = Some(timeout);
+        self.build_client();
+        self
+    }
+
+    /// Set timeout for each write operation.
+    pub fn write_timeout(mut self, timeout: Duration) -> HTTPRequest {
+        self.timeouts.write = Some(timeout);
+        self.build_client();
+        self
+    }
+
+    /// Set overall deadline of each request.
+    ///
+    /// The deadline covers connecting, sending request, reading response
+    /// headers and reading response with `receive`.
+    /// Timed out operation returns `Error::Timeout`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate ruroonga_client as groonga;
+    ///
+    /// use std::time::Duration;
+    ///
+    /// groonga::HTTPRequest::new()
+    ///   .connect_timeout(Duration::from_secs(1))
+    ///   .read_timeout(Duration::from_secs(5))
+    ///   .deadline(Duration::from_secs(10));
+    /// ```
+    pub fn deadline(mut self, deadline: Duration) -> HTTPRequest {
+        self.timeouts.deadline = Some(deadline);
+        self.build_client();
+        self
+    }
+
+    /// Retry failed requests with specified policy.
+    ///
+    /// `get` requests are retried unless the command modifies database.
+    /// `load` requests are retried only if `idempotent_only(false)` is specified.
+    pub fn retry(mut self, policy: RetryPolicy) -> HTTPRequest {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Set base uri which is used to send `GroongaClient` commands.
+    ///
+    /// Default value is `URIBase::new().build()`, i.e. `"http://localhost:10041"`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate ruroonga_client as groonga;
+    ///
+    /// let uri_base = groonga::URIBase::new().port(20041).build();
+    /// groonga::HTTPRequest::new()
+    ///   .with_uri_base(uri_base);
+    /// ```
+    pub fn with_uri_base<T>(mut self, uri_base: T) -> HTTPRequest
+        where T: Into<String>
+    {
+        self.uri_base = uri_base.into();
+        self
+    }
+
     /// Creating an outgoing request with HTTP.
-    pub fn get<T>(&mut self, url: T) -> Result<Response, HyperError>
+    pub fn get<T>(&mut self, url: T) -> Result<Response, Error>
+        where T: AsRef<str>
+    {
+        let idempotent = is_idempotent_url(url.as_ref());
+        match self.retry.clone() {
+            Some(policy) => policy.run(idempotent, || self.send_get(url.as_ref())),
+            None => self.send_get(url),
+        }
+    }
+
+    fn send_get<T>(&mut self, url: T) -> Result<Response, Error>
         where T: AsRef<str>
     {
         let mut headers = Headers::new();
@@ -64,14 +312,35 @@ impl HTTPRequest {
                                           password: self.password.clone(),
                                       }));
         }
-        headers.set(Connection::close());
-        self.client.get(url.as_ref()).headers(headers).send()
+        match self.pool {
+            Some(ref mut pool) => {
+                pool.requests += 1;
+                headers.set(Connection::keep_alive());
+            }
+            None => headers.set(Connection::close()),
+        }
+        try!(self.start_request());
+        Ok(try!(self.client.get(url.as_ref()).headers(headers).send()))
     }
 
     /// Creating an loading data request via POST.
-    pub fn load<T>(&mut self, url: T, body: String) -> Result<Response, HyperError>
+    pub fn load<T>(&mut self, url: T, body: String) -> Result<Response, Error>
+        where T: AsRef<str>
+    {
+        match self.retry.clone() {
+            Some(policy) => policy.run(false, || self.send_load(url.as_ref(), &body)),
+            None => self.send_load(url, &body),
+        }
+    }
+
+    fn send_load<T>(&mut self, url: T, body: &str) -> Result<Response, Error>
         where T: AsRef<str>
     {
+        self.send_post(url.as_ref(), Body::BufBody(body.as_bytes(), body.len()))
+    }
+
+    // Send POST request. Body without its size is sent with chunked transfer encoding.
+    fn send_post<'b>(&'b mut self, url: &str, body: Body<'b>) -> Result<Response, Error> {
         let mut headers = Headers::new();
         if self.auth {
             headers.set(Authorization(Basic {
@@ -79,27 +348,118 @@ impl HTTPRequest {
                                           password: self.password.clone(),
                                       }));
         }
+        if let Some(ref mut pool) = self.pool {
+            pool.requests += 1;
+            headers.set(Connection::keep_alive());
+        }
         headers.set(ContentType::json());
-        headers.set(ContentLength(body.len() as u64));
-        self.client
-            .post(url.as_ref())
-            .headers(headers)
-            .body(&*body)
-            .send()
+        try!(self.start_request());
+        Ok(try!(self.client
+                    .post(url)
+                    .headers(headers)
+                    .body(body)
+                    .send()))
     }
 
     /// Read the Response.
-    pub fn receive(&mut self, res: &mut Response) -> Result<String, io::Error> {
-        let mut body = String::new();
-        try!(res.read_to_string(&mut body));
+    pub fn receive(&mut self, res: &mut Response) -> Result<String, Error> {
+        let started = match self.started {
+            Some(started) if self.timeouts.deadline.is_some() => started,
+            _ => {
+                let mut body = String::new();
+                try!(res.read_to_string(&mut body));
+                return Ok(body);
+            }
+        };
+        let mut body = vec![];
+        let mut buf = [0; 8192];
+        loop {
+            try!(res.get_ref().set_read_timeout(try!(self.timeouts.read_timeout(started))));
+            match res.read(&mut buf) {
+                Ok(0) => break,
+                Ok(size) => body.extend_from_slice(&buf[..size]),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(Error::from(err)),
+            }
+        }
+        Ok(try!(String::from_utf8(body)))
+    }
+}
+
+fn is_idempotent_url(url: &str) -> bool {
+    match url.find("://") {
+        Some(pos) => {
+            let rest = &url[pos + 3..];
+            let path = rest.find('/').map(|pos| &rest[pos..]).unwrap_or("");
+            retry::is_idempotent(retry::command_name(path))
+        }
+        None => retry::is_idempotent(retry::command_name(url)),
+    }
+}
 
+impl HTTPRequest {
+    // Send request and read whole response without retry.
+    fn exchange(&mut self, url: &str, body: Option<&str>) -> Result<String, Error> {
+        let mut res = match body {
+            Some(body) => try!(self.send_load(url, body)),
+            None => try!(self.send_get(url)),
+        };
+        self.receive_result(&mut res, body.is_some())
+    }
+
+    // Read whole response and check HTTP status.
+    //
+    // Response of `load` with failed records has error status too, so its
+    // body is returned if it has the load result.
+    fn receive_result(&mut self, res: &mut Response, is_load: bool) -> Result<String, Error> {
+        let body = try!(self.receive(res));
+        if !res.status.is_success() {
+            // Groonga also returns error response with non-successful status.
+            if let Ok(result) = ResultParser::parse(&body) {
+                if is_load && result.load_result().is_ok() {
+                    return Ok(body);
+                }
+                try!(result.check_status());
+            }
+            return Err(Error::HttpStatus(res.status.to_u16(), body));
+        }
         Ok(body)
     }
 }
 
+impl GroongaClient for HTTPRequest {
+    fn execute<'a>(&mut self,
+                   command: &CommandQuery<'a>,
+                   body: Option<&str>)
+                   -> Result<ResultParser, Error> {
+        let url = RequestURI::new(self.uri_base.clone(), command.encode()).url();
+        let idempotent = body.is_none() && retry::is_idempotent(&command.get_command());
+        let result = match self.retry.clone() {
+            Some(policy) => try!(policy.run(idempotent, || self.exchange(&url, body))),
+            None => try!(self.exchange(&url, body)),
+        };
+        ResultParser::parse(&result)
+    }
+
+    /// Send `body` with chunked transfer encoding.
+    fn execute_stream<'a>(&mut self,
+                          command: &CommandQuery<'a>,
+                          body: &mut Read)
+                          -> Result<ResultParser, Error> {
+        let url = RequestURI::new(self.uri_base.clone(), command.encode()).url();
+        let mut res = try!(self.send_post(&url, Body::ChunkedBody(body)));
+        let result = try!(self.receive_result(&mut res, true));
+        ResultParser::parse(&result)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::thread;
+    use return_code::ReturnCode;
 
     #[test]
     fn use_auth() {
@@ -118,4 +478,296 @@ mod tests {
         let req = HTTPRequest::new();
         assert_eq!(false, req.auth)
     }
+
+    #[test]
+    fn default_uri_base() {
+        let req = HTTPRequest::new();
+        assert_eq!("http://localhost:10041", req.uri_base)
+    }
+
+    #[test]
+    fn use_uri_base() {
+        let req = HTTPRequest::new().with_uri_base("http://127.0.0.1:20041");
+        assert_eq!("http://127.0.0.1:20041", req.uri_base)
+    }
+
+    #[test]
+    fn dont_use_keep_alive() {
+        let req = HTTPRequest::new();
+        assert_eq!(None, req.pool_stats())
+    }
+
+    #[test]
+    fn reuse_keep_alive_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            for _ in 0..2 {
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).unwrap();
+                let body = "[[0,1452348610.39281,0.000101566314697266],true]";
+                write!(stream,
+                       "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                       body.len(),
+                       body)
+                    .unwrap();
+            }
+        });
+        let mut req = HTTPRequest::new()
+            .with_uri_base(format!("http://{}", addr))
+            .keep_alive(PoolConfig::new());
+        let command = CommandQuery::new("status");
+        req.execute(&command, None).unwrap();
+        req.execute(&command, None).unwrap();
+        server.join().unwrap();
+        let stats = req.pool_stats().unwrap();
+        assert_eq!(1, stats.hits());
+        assert_eq!(1, stats.misses());
+    }
+
+    #[test]
+    fn idempotent_url() {
+        assert!(is_idempotent_url("http://localhost:10041/d/select?table=Sites"));
+        assert!(!is_idempotent_url("http://localhost:10041/d/load?table=Sites"));
+        assert!(!is_idempotent_url("/d/table_create?name=Sites"));
+    }
+
+    #[test]
+    fn retry_refused_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let observed = attempts.clone();
+        let policy = RetryPolicy::new()
+            .backoff(Duration::from_millis(1), Duration::from_millis(1))
+            .on_retry(move |_| {
+                observed.fetch_add(1, Ordering::SeqCst);
+            });
+        let mut req = HTTPRequest::new()
+            .with_uri_base(format!("http://{}", addr))
+            .retry(policy);
+        assert!(req.execute(&CommandQuery::new("status"), None).is_err());
+        assert_eq!(2, attempts.load(Ordering::SeqCst));
+        assert!(req.execute(&CommandQuery::new("load"), Some("[]")).is_err());
+        assert_eq!(2, attempts.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn http_status_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).unwrap();
+            let body = "<html>Bad Gateway</html>";
+            write!(stream,
+                   "HTTP/1.1 502 Bad Gateway\r\nContent-Length: {}\r\n\r\n{}",
+                   body.len(),
+                   body)
+                .unwrap();
+        });
+        let mut req = HTTPRequest::new().with_uri_base(format!("http://{}", addr));
+        match req.execute(&CommandQuery::new("status"), None) {
+            Err(Error::HttpStatus(502, body)) => assert_eq!("<html>Bad Gateway</html>", body),
+            _ => panic!("request should be failed with HTTP status"),
+        }
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn groonga_error_with_http_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).unwrap();
+            let body = r#"[[-22,1337566253.89858,0.000355720520019531,"invalid table name: <Nonexistent>"]]"#;
+            write!(stream,
+                   "HTTP/1.1 400 Bad Request\r\nContent-Length: {}\r\n\r\n{}",
+                   body.len(),
+                   body)
+                .unwrap();
+        });
+        let mut req = HTTPRequest::new().with_uri_base(format!("http://{}", addr));
+        match req.execute(&CommandQuery::new("select"), None) {
+            Err(Error::Groonga { code, message }) => {
+                assert_eq!(ReturnCode::InvalidArgument, code);
+                assert_eq!("invalid table name: <Nonexistent>", message);
+            }
+            _ => panic!("request should be failed with Groonga error"),
+        }
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn read_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).unwrap();
+            // Keep connection without response until client gives up.
+            let _ = stream.read(&mut buf);
+        });
+        let mut req = HTTPRequest::new()
+            .with_uri_base(format!("http://{}", addr))
+            .read_timeout(Duration::from_millis(100));
+        let command = CommandQuery::new("status");
+        match req.execute(&command, None) {
+            Err(Error::Timeout) => (),
+            _ => panic!("request should be timed out"),
+        }
+        drop(req);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn deadline_while_receiving() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).unwrap();
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n[[0,").unwrap();
+            let _ = stream.read(&mut buf);
+        });
+        let mut req = HTTPRequest::new()
+            .with_uri_base(format!("http://{}", addr))
+            .deadline(Duration::from_millis(200));
+        let mut res = req.get(format!("http://{}/d/status", addr)).unwrap();
+        match req.receive(&mut res) {
+            Err(Error::Timeout) => (),
+            _ => panic!("request should be timed out"),
+        }
+        drop(res);
+        drop(req);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn deadline_with_slow_drip_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).unwrap();
+            // Each byte arrives within read timeout, but the whole response does not.
+            for byte in b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n[]".iter() {
+                if stream.write_all(&[*byte]).is_err() {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+        let mut req = HTTPRequest::new()
+            .with_uri_base(format!("http://{}", addr))
+            .read_timeout(Duration::from_secs(1))
+            .deadline(Duration::from_millis(300));
+        let started = Instant::now();
+        match req.execute(&CommandQuery::new("status"), None) {
+            Err(Error::Timeout) => (),
+            result => panic!("request should be timed out: {:?}", result.is_ok()),
+        }
+        assert!(started.elapsed() < Duration::from_millis(1000));
+        drop(req);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn execute_with_groonga_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 1024];
+            let size = stream.read(&mut buf).unwrap();
+            let body = "[[0,1452348610.39281,0.000101566314697266],true]";
+            write!(stream,
+                   "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                   body.len(),
+                   body)
+                .unwrap();
+            String::from_utf8_lossy(&buf[..size]).into_owned()
+        });
+        let mut req = HTTPRequest::new().with_uri_base(format!("http://{}", addr));
+        let command = CommandQuery::new("status");
+        let result = req.execute(&command, None).unwrap();
+        assert_eq!(0, result.status().unwrap());
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /d/status? HTTP/1.1"));
+    }
+
+    #[test]
+    fn load_errors_with_http_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"]") {
+                let size = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..size]);
+            }
+            let body = r#"{"header":{"return_code":-22,"start_time":0.0,"elapsed_time":0.0,
+                                     "error":{"message":"empty key"}},
+                           "body":{"n_loaded_records":1,
+                                   "errors":[{"return_code":0,"message":null},
+                                             {"return_code":-22,"message":"empty key"}]}}"#;
+            write!(stream,
+                   "HTTP/1.1 400 Bad Request\r\nContent-Length: {}\r\n\r\n{}",
+                   body.len(),
+                   body)
+                .unwrap();
+        });
+        let mut req = HTTPRequest::new().with_uri_base(format!("http://{}", addr));
+        let mut command = CommandQuery::new("load");
+        command.set_argument(vec![("table", "Sites"), ("output_errors", "yes")]);
+        let result = req.execute(&command, Some(r#"[{"_key":"a"},{"_key":""}]"#))
+            .and_then(|result| result.load_result())
+            .unwrap();
+        assert_eq!(1, result.n_loaded_records());
+        assert_eq!(1, result.errors()[0].index());
+        assert_eq!(Some("empty key"), result.errors()[0].message());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn execute_stream_with_chunked_body() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buf = [0; 1024];
+            while !String::from_utf8_lossy(&request).ends_with("0\r\n\r\n") {
+                let size = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..size]);
+            }
+            let body = "[[0,1452348610.39281,0.000101566314697266],1]";
+            write!(stream,
+                   "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                   body.len(),
+                   body)
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        let mut req = HTTPRequest::new().with_uri_base(format!("http://{}", addr));
+        let mut command = CommandQuery::new("load");
+        command.set_argument(vec![("table", "Sites")]);
+        let mut body = &br#"[{"_key":"http://example.org/"}]"#[..];
+        let result = req.execute_stream(&command, &mut body).unwrap();
+        assert_eq!(1, result.load_result().unwrap().n_loaded_records());
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /d/load?table=Sites HTTP/1.1"));
+        assert!(request.contains("Transfer-Encoding: chunked"));
+        assert!(request.contains(r#"[{"_key":"http://example.org/"}]"#));
+    }
 }
@@ -0,0 +1,315 @@
+use std::io;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use hyper::error::Error as HyperError;
+use hyper::net::{HttpStream, NetworkStream, SslClient};
+use openssl::error::ErrorStack;
+use openssl::ssl::{SslConnector, SslFiletype, SslMethod, SslStream, SslVerifyMode};
+
+/// TLS settings for `HTTPRequest`.
+#[derive(Clone, Debug)]
+pub struct TlsConfig {
+    ca_file: Option<PathBuf>,
+    certificate: Option<(PathBuf, PathBuf)>,
+    verify: bool,
+}
+
+impl Default for TlsConfig {
+    fn default() -> TlsConfig {
+        TlsConfig {
+            ca_file: None,
+            certificate: None,
+            verify: true,
+        }
+    }
+}
+
+impl TlsConfig {
+    ///
+    /// Create TlsConfig struct.
+    ///
+    /// Default values are:
+    ///
+    ///   ca_file: system default CA certificates
+    ///
+    ///   certificate: no client certificate
+    ///
+    ///   verify: true
+    ///
+    pub fn new() -> TlsConfig {
+        TlsConfig::default()
+    }
+
+    /// Set PEM formatted CA bundle to verify server certificate.
+    pub fn ca_file<P>(mut self, ca_file: P) -> TlsConfig
+        where P: AsRef<Path>
+    {
+        self.ca_file = Some(ca_file.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set PEM formatted client certificate chain and its private key.
+    pub fn client_certificate<P>(mut self, certificate: P, private_key: P) -> TlsConfig
+        where P: AsRef<Path>
+    {
+        self.certificate = Some((certificate.as_ref().to_path_buf(),
+                                 private_key.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Disable server certificate and hostname verification.
+    ///
+    /// This is insecure. Use this only for development servers
+    /// which use self-signed certificates.
+    pub fn danger_disable_verification(mut self) -> TlsConfig {
+        self.verify = false;
+        self
+    }
+
+    #[doc(hidden)]
+    pub fn build(&self) -> Result<TlsClient, HyperError> {
+        self.build_connector()
+            .map(|connector| {
+                TlsClient {
+                    connector: Arc::new(connector),
+                    verify: self.verify,
+                }
+            })
+            .map_err(|err| HyperError::Ssl(Box::new(err)))
+    }
+
+    fn build_connector(&self) -> Result<SslConnector, ErrorStack> {
+        let mut builder = try!(SslConnector::builder(SslMethod::tls()));
+        if let Some(ref ca_file) = self.ca_file {
+            try!(builder.set_ca_file(ca_file));
+        }
+        if let Some((ref certificate, ref private_key)) = self.certificate {
+            try!(builder.set_certificate_chain_file(certificate));
+            try!(builder.set_private_key_file(private_key, SslFiletype::PEM));
+            try!(builder.check_private_key());
+        }
+        if !self.verify {
+            builder.set_verify(SslVerifyMode::NONE);
+        }
+        Ok(builder.build())
+    }
+}
+
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct TlsClient {
+    connector: Arc<SslConnector>,
+    verify: bool,
+}
+
+impl SslClient for TlsClient {
+    type Stream = TlsStream;
+
+    fn wrap_client(&self, stream: HttpStream, host: &str) -> ::hyper::Result<TlsStream> {
+        let configuration = try!(self.connector
+                                     .configure()
+                                     .map_err(|err| HyperError::Ssl(Box::new(err))));
+        match configuration.verify_hostname(self.verify).connect(host, stream) {
+            Ok(stream) => Ok(TlsStream(Arc::new(Mutex::new(stream)))),
+            Err(err) => Err(HyperError::Ssl(Box::new(io::Error::new(io::ErrorKind::Other,
+                                                                     err.to_string())))),
+        }
+    }
+}
+
+// hyper requires that TLS stream is cloneable.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct TlsStream(Arc<Mutex<SslStream<HttpStream>>>);
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().read(buf)
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
+
+impl NetworkStream for TlsStream {
+    fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+        self.0.lock().unwrap().get_mut().peer_addr()
+    }
+
+    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.0.lock().unwrap().get_ref().set_read_timeout(dur)
+    }
+
+    fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.0.lock().unwrap().get_ref().set_write_timeout(dur)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::{self, File};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::thread::JoinHandle;
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::BigNum;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::{PKey, Private};
+    use openssl::rsa::Rsa;
+    use openssl::ssl::SslAcceptor;
+    use openssl::x509::{X509, X509NameBuilder};
+    use openssl::x509::extension::{BasicConstraints, SubjectAlternativeName};
+    use builtin::command_query::CommandQuery;
+    use client::GroongaClient;
+    use error::Error;
+    use http_request::HTTPRequest;
+    use uri_base::URIBase;
+
+    fn self_signed(common_name: &str) -> (X509, PKey<Private>) {
+        let pkey = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", common_name).unwrap();
+        let name = name.build();
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        let serial = BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap();
+        builder.set_serial_number(&serial).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&pkey).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+        let san = SubjectAlternativeName::new()
+            .dns(common_name)
+            .build(&builder.x509v3_context(None, None))
+            .unwrap();
+        builder.append_extension(san).unwrap();
+        builder.append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+            .unwrap();
+        builder.sign(&pkey, MessageDigest::sha256()).unwrap();
+        (builder.build(), pkey)
+    }
+
+    // PEM file which is removed when the test finishes.
+    struct PemFile(PathBuf);
+
+    impl AsRef<Path> for PemFile {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for PemFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn write_pem(name: &str, pem: Vec<u8>) -> PemFile {
+        let path = env::temp_dir().join(format!("ruroonga_client_{}_{}.pem",
+                                                 name,
+                                                 thread::current().name().unwrap_or("test")
+                                                     .replace("::", "_")));
+        File::create(&path).unwrap().write_all(&pem).unwrap();
+        PemFile(path)
+    }
+
+    // Stand-in for groonga-httpd: accept one TLS connection and return `status` response.
+    fn serve_once(acceptor: SslAcceptor) -> (u16, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut stream = match acceptor.accept(stream) {
+                Ok(stream) => stream,
+                Err(_) => return,
+            };
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).unwrap();
+            let body = "[[0,1452348610.39281,0.000101566314697266],true]";
+            write!(stream,
+                   "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                   body.len(),
+                   body)
+                .unwrap();
+        });
+        (port, server)
+    }
+
+    fn server_acceptor(certificate: &X509, private_key: &PKey<Private>) -> SslAcceptor {
+        let mut acceptor = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+        acceptor.set_certificate(certificate).unwrap();
+        acceptor.set_private_key(private_key).unwrap();
+        acceptor.build()
+    }
+
+    fn request_status(port: u16, config: TlsConfig) -> Result<(), Error> {
+        let uri_base = URIBase::new().scheme("https").port(port).build();
+        let mut req = try!(HTTPRequest::new().with_uri_base(uri_base).tls(config));
+        let command = CommandQuery::new("status");
+        let result = try!(req.execute(&command, None));
+        assert_eq!(0, result.status().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn connect_with_ca_file() {
+        let (certificate, private_key) = self_signed("localhost");
+        let ca_file = write_pem("ca", certificate.to_pem().unwrap());
+        let (port, server) = serve_once(server_acceptor(&certificate, &private_key));
+        request_status(port, TlsConfig::new().ca_file(&ca_file)).unwrap();
+        server.join().unwrap();
+        let path = ca_file.0.clone();
+        drop(ca_file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn reject_unknown_certificate() {
+        let (certificate, private_key) = self_signed("localhost");
+        let (port, server) = serve_once(server_acceptor(&certificate, &private_key));
+        assert!(request_status(port, TlsConfig::new()).is_err());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn connect_without_verification() {
+        let (certificate, private_key) = self_signed("groonga.example");
+        let (port, server) = serve_once(server_acceptor(&certificate, &private_key));
+        request_status(port, TlsConfig::new().danger_disable_verification()).unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn connect_with_client_certificate() {
+        let (certificate, private_key) = self_signed("localhost");
+        let (client_certificate, client_private_key) = self_signed("client");
+        let ca_file = write_pem("server", certificate.to_pem().unwrap());
+        let certificate_file = write_pem("client", client_certificate.to_pem().unwrap());
+        let private_key_file = write_pem("client_key",
+                                         client_private_key.private_key_to_pem_pkcs8().unwrap());
+        let mut acceptor = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+        acceptor.set_certificate(&certificate).unwrap();
+        acceptor.set_private_key(&private_key).unwrap();
+        acceptor.cert_store_mut().add_cert(client_certificate).unwrap();
+        acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        let (port, server) = serve_once(acceptor.build());
+        let config = TlsConfig::new()
+            .ca_file(&ca_file)
+            .client_certificate(&certificate_file, &private_key_file);
+        request_status(port, config).unwrap();
+        server.join().unwrap();
+    }
+}
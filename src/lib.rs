@@ -1,20 +1,68 @@
 extern crate hyper;
 extern crate url;
 extern crate json_flex;
+extern crate serde;
+extern crate serde_json;
+#[cfg(test)]
+#[macro_use]
+extern crate serde_derive;
 #[cfg(feature="gqtp")]
 extern crate byteorder;
+#[cfg(feature="ssl")]
+extern crate openssl;
 
+mod bulk_loader;
+mod client;
+mod error;
+mod groonga_table;
+mod groonga_value;
+mod http_pool;
 mod http_request;
+#[cfg(feature="ssl")]
+mod http_tls;
+mod load_result;
 mod result_parser;
 mod request_uri;
+mod response_header;
+mod retry;
+mod return_code;
+mod schema;
+mod timeout;
 mod uri_base;
 pub mod builtin;
 #[cfg(feature="gqtp")]
+mod gqtp_connection;
+#[cfg(feature="gqtp")]
+mod gqtp_frame;
+#[cfg(feature="gqtp")]
 mod gqtp_request;
+#[cfg(feature="gqtp")]
+mod gqtp_response;
 
+pub use bulk_loader::{BatchReport, BulkLoader, BulkLoadReport, InvalidRecord};
+pub use client::GroongaClient;
+pub use error::Error;
+pub use groonga_table::GroongaTable;
+pub use groonga_value::{GeoDatum, GeoPoint, GroongaValue};
+pub use http_pool::{PoolConfig, PoolStats};
 pub use http_request::HTTPRequest;
-pub use result_parser::{ResultParser, Rows};
+#[cfg(feature="ssl")]
+pub use http_tls::TlsConfig;
+pub use load_result::{LoadError, LoadResult};
+pub use result_parser::{ColumnDef, Record, Records, ResultParser, ResultSet, Rows, TypedRecord};
 pub use request_uri::RequestURI;
+pub use response_header::{ErrorLocation, ResponseHeader};
+pub use retry::{RetryEvent, RetryPolicy};
+pub use return_code::ReturnCode;
+pub use schema::{LiveColumn, LiveSchema, LiveTable, Migration, Schema, SchemaChange};
 pub use uri_base::URIBase;
 #[cfg(feature="gqtp")]
-pub use gqtp_request::GQTPRequest;
+pub use gqtp_connection::{GQTPConnection, GQTPPool, PooledGQTPConnection};
+#[cfg(feature="gqtp")]
+pub use gqtp_frame::{GQTPContentType, GQTPFrame, GQTPHeader, GQTP_FLAG_HEAD, GQTP_FLAG_MORE,
+                     GQTP_FLAG_QUIET, GQTP_FLAG_QUIT, GQTP_FLAG_TAIL, GQTP_HEADER_SIZE,
+                     GQTP_PROTOCOL};
+#[cfg(feature="gqtp")]
+pub use gqtp_request::{GQTPRequest, GQTPError};
+#[cfg(feature="gqtp")]
+pub use gqtp_response::GQTPResponse;
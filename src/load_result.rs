@@ -0,0 +1,142 @@
+use serde_json::Value;
+use error::Error;
+use return_code::ReturnCode;
+
+/// Error of a record which is reported by `output_errors`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadError {
+    index: usize,
+    return_code: ReturnCode,
+    message: Option<String>,
+}
+
+impl LoadError {
+    /// Return index of the record in loaded values.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Return Groonga's return code.
+    pub fn return_code(&self) -> ReturnCode {
+        self.return_code
+    }
+
+    /// Return error message.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_ref().map(|message| message.as_str())
+    }
+}
+
+/// Result of `load` command.
+///
+/// Groonga returns a number of loaded records as body.
+/// With `command_version=3`, body is an object such as:
+///
+/// `{"n_loaded_records": 1, "loaded_ids": [1, 0], "errors": [...]}`
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadResult {
+    n_loaded_records: u64,
+    loaded_ids: Option<Vec<u64>>,
+    errors: Vec<LoadError>,
+}
+
+impl LoadResult {
+    #[doc(hidden)]
+    pub fn from_body(body: &Value) -> Result<LoadResult, Error> {
+        if let Some(n_loaded_records) = body.as_u64() {
+            return Ok(LoadResult {
+                n_loaded_records: n_loaded_records,
+                loaded_ids: None,
+                errors: vec![],
+            });
+        }
+        let n_loaded_records = try!(body.get("n_loaded_records")
+            .and_then(|n| n.as_u64())
+            .ok_or_else(|| Error::Json("load result does not have n_loaded_records".to_owned())));
+        let loaded_ids = match body.get("loaded_ids") {
+            Some(ids) => {
+                let ids = try!(ids.as_array()
+                    .and_then(|ids| ids.iter().map(|id| id.as_u64()).collect())
+                    .ok_or_else(|| Error::Json("loaded_ids is not array of ID".to_owned())));
+                Some(ids)
+            }
+            None => None,
+        };
+        let errors: Vec<Option<LoadError>> = match body.get("errors") {
+            Some(&Value::Array(ref errors)) => {
+                try!(errors.iter().enumerate().map(parse_error).collect())
+            }
+            _ => vec![],
+        };
+        Ok(LoadResult {
+            n_loaded_records: n_loaded_records,
+            loaded_ids: loaded_ids,
+            errors: errors.into_iter().filter_map(|error| error).collect(),
+        })
+    }
+
+    /// Return a number of loaded records.
+    pub fn n_loaded_records(&self) -> u64 {
+        self.n_loaded_records
+    }
+
+    /// Return IDs of loaded records if `output_ids` is specified.
+    ///
+    /// ID of a record which is not loaded is `0`.
+    pub fn loaded_ids(&self) -> Option<&[u64]> {
+        self.loaded_ids.as_ref().map(|ids| ids.as_slice())
+    }
+
+    /// Return errors of records which are not loaded
+    /// if `output_errors` is specified.
+    pub fn errors(&self) -> &[LoadError] {
+        &self.errors
+    }
+}
+
+// Error is `{"return_code": -22, "message": "..."}`. Loaded record has `0`.
+fn parse_error((index, error): (usize, &Value)) -> Result<Option<LoadError>, Error> {
+    let return_code = try!(error.get("return_code")
+        .and_then(|code| code.as_i64())
+        .map(ReturnCode::from_code)
+        .ok_or_else(|| Error::Json(format!("error of record {} does not have code", index))));
+    if return_code.is_success() {
+        return Ok(None);
+    }
+    Ok(Some(LoadError {
+        index: index,
+        return_code: return_code,
+        message: error.get("message").and_then(|message| message.as_str()).map(|m| m.to_owned()),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn parse_loaded_count() {
+        let result = LoadResult::from_body(&serde_json::from_str("2").unwrap()).unwrap();
+        assert_eq!(2, result.n_loaded_records());
+        assert_eq!(None, result.loaded_ids());
+        assert!(result.errors().is_empty());
+    }
+
+    #[test]
+    fn parse_ids_and_errors() {
+        let body = r#"{"n_loaded_records":2,
+                       "loaded_ids":[1,0,2],
+                       "errors":[{"return_code":0,"message":null},
+                                 {"return_code":-22,"message":"<Sites.n_likes>: failed to cast"},
+                                 {"return_code":0,"message":null}]}"#;
+        let result = LoadResult::from_body(&serde_json::from_str(body).unwrap()).unwrap();
+        assert_eq!(2, result.n_loaded_records());
+        assert_eq!(Some(&[1, 0, 2][..]), result.loaded_ids());
+        assert_eq!(1, result.errors().len());
+        assert_eq!(1, result.errors()[0].index());
+        assert_eq!(ReturnCode::InvalidArgument, result.errors()[0].return_code());
+        assert_eq!(Some("<Sites.n_likes>: failed to cast"),
+                   result.errors()[0].message());
+    }
+}
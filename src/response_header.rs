@@ -0,0 +1,220 @@
+use serde_json::Value;
+use error::Error;
+use return_code::ReturnCode;
+
+/// Location in Groonga's source code where an error occurred.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ErrorLocation {
+    function: String,
+    file: String,
+    line: u64,
+}
+
+impl ErrorLocation {
+    /// Return function name.
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    /// Return source file name.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Return line number.
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+}
+
+/// Typed response header.
+///
+/// Groonga returns header as following array:
+///
+/// `[return_code, start_time, elapsed_time, error_message, error_location]`
+///
+/// `error_message` and `error_location` exist only in error response.
+///
+/// With `command_version=3`, header is an object which has
+/// `return_code`, `start_time`, `elapsed_time` and `error` keys.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResponseHeader {
+    return_code: ReturnCode,
+    start_time: f64,
+    elapsed_time: f64,
+    error_message: Option<String>,
+    error_location: Option<ErrorLocation>,
+}
+
+impl ResponseHeader {
+    #[doc(hidden)]
+    pub fn from_value(header: &Value) -> Result<ResponseHeader, Error> {
+        match *header {
+            Value::Array(ref header) => ResponseHeader::from_array(header),
+            Value::Object(_) => ResponseHeader::from_object(header),
+            _ => Err(Error::Json("header is not array nor object".to_owned())),
+        }
+    }
+
+    fn from_array(header: &[Value]) -> Result<ResponseHeader, Error> {
+        let error_message = header.get(3)
+            .and_then(|message| message.as_str())
+            .map(|message| message.to_owned());
+        // Error location is `[["function", "file", line], ...]`.
+        let location = header.get(4).and_then(|locations| locations.get(0));
+        let error_location = parse_location(location.and_then(|location| location.get(0)),
+                                            location.and_then(|location| location.get(1)),
+                                            location.and_then(|location| location.get(2)));
+        ResponseHeader::new(header.get(0), header.get(1), header.get(2))
+            .map(|header| header.error(error_message, error_location))
+    }
+
+    // Error is `{"message": "...", "function": "...", "file": "...", "line": 897}`.
+    fn from_object(header: &Value) -> Result<ResponseHeader, Error> {
+        let error = header.get("error");
+        let error_message = error.and_then(|error| error.get("message"))
+            .and_then(|message| message.as_str())
+            .map(|message| message.to_owned());
+        let error_location = parse_location(error.and_then(|error| error.get("function")),
+                                            error.and_then(|error| error.get("file")),
+                                            error.and_then(|error| error.get("line")));
+        ResponseHeader::new(header.get("return_code"),
+                            header.get("start_time"),
+                            header.get("elapsed_time"))
+            .map(|header| header.error(error_message, error_location))
+    }
+
+    fn new(return_code: Option<&Value>,
+           start_time: Option<&Value>,
+           elapsed_time: Option<&Value>)
+           -> Result<ResponseHeader, Error> {
+        let return_code = try!(return_code.and_then(|code| code.as_i64())
+            .ok_or_else(|| Error::Json("header does not have return code".to_owned())));
+        let start_time = try!(start_time.and_then(|time| time.as_f64())
+            .ok_or_else(|| Error::Json("header does not have start time".to_owned())));
+        let elapsed_time = try!(elapsed_time.and_then(|time| time.as_f64())
+            .ok_or_else(|| Error::Json("header does not have elapsed time".to_owned())));
+        Ok(ResponseHeader {
+            return_code: ReturnCode::from_code(return_code),
+            start_time: start_time,
+            elapsed_time: elapsed_time,
+            error_message: None,
+            error_location: None,
+        })
+    }
+
+    fn error(mut self,
+             message: Option<String>,
+             location: Option<ErrorLocation>)
+             -> ResponseHeader {
+        self.error_message = message;
+        self.error_location = location;
+        self
+    }
+
+    /// Return Groonga's return code.
+    pub fn return_code(&self) -> ReturnCode {
+        self.return_code
+    }
+
+    /// Return whether request succeeded.
+    pub fn is_success(&self) -> bool {
+        self.return_code.is_success()
+    }
+
+    /// Return start time as UNIX time.
+    pub fn start_time(&self) -> f64 {
+        self.start_time
+    }
+
+    /// Return elapsed time in seconds.
+    pub fn elapsed_time(&self) -> f64 {
+        self.elapsed_time
+    }
+
+    /// Return error message in error response.
+    pub fn error_message(&self) -> Option<&str> {
+        self.error_message.as_ref().map(|message| message.as_str())
+    }
+
+    /// Return error location in error response.
+    pub fn error_location(&self) -> Option<&ErrorLocation> {
+        self.error_location.as_ref()
+    }
+}
+
+fn parse_location(function: Option<&Value>,
+                  file: Option<&Value>,
+                  line: Option<&Value>)
+                  -> Option<ErrorLocation> {
+    match (function.and_then(|v| v.as_str()),
+           file.and_then(|v| v.as_str()),
+           line.and_then(|v| v.as_u64())) {
+        (Some(function), Some(file), Some(line)) => {
+            Some(ErrorLocation {
+                function: function.to_owned(),
+                file: file.to_owned(),
+                line: line,
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn success_header() {
+        let value = serde_json::from_str("[0,1452348610.39281,0.000101566314697266]").unwrap();
+        let header = ResponseHeader::from_value(&value).unwrap();
+        assert!(header.is_success());
+        assert_eq!(ReturnCode::Success, header.return_code());
+        assert_eq!(1452348610.39281, header.start_time());
+        assert_eq!(0.000101566314697266, header.elapsed_time());
+        assert_eq!(None, header.error_message());
+        assert_eq!(None, header.error_location());
+    }
+
+    #[test]
+    fn error_header() {
+        let value = serde_json::from_str(r#"[-22,1337566253.89858,0.000355720520019531,
+                                             "invalid table name: <Nonexistent>",
+                                             [["grn_select","proc.c",897]]]"#)
+            .unwrap();
+        let header = ResponseHeader::from_value(&value).unwrap();
+        assert!(!header.is_success());
+        assert_eq!(ReturnCode::InvalidArgument, header.return_code());
+        assert_eq!(Some("invalid table name: <Nonexistent>"),
+                   header.error_message());
+        let location = header.error_location().unwrap();
+        assert_eq!("grn_select", location.function());
+        assert_eq!("proc.c", location.file());
+        assert_eq!(897, location.line());
+    }
+
+    #[test]
+    fn command_version_3_header() {
+        let value = serde_json::from_str(r#"{"return_code":-22,
+                                             "start_time":1337566253.89858,
+                                             "elapsed_time":0.000355720520019531,
+                                             "error":{"message":"invalid table name",
+                                                      "function":"grn_select",
+                                                      "file":"proc.c",
+                                                      "line":897}}"#)
+            .unwrap();
+        let header = ResponseHeader::from_value(&value).unwrap();
+        assert_eq!(ReturnCode::InvalidArgument, header.return_code());
+        assert_eq!(1337566253.89858, header.start_time());
+        assert_eq!(Some("invalid table name"), header.error_message());
+        assert_eq!(897, header.error_location().unwrap().line());
+    }
+
+    #[test]
+    fn corrupted_header() {
+        let value = serde_json::from_str(r#"["0",1452348610.39281]"#).unwrap();
+        assert!(ResponseHeader::from_value(&value).is_err());
+    }
+}
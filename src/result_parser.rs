@@ -1,39 +1,305 @@
-use json_flex;
+use std::collections::{BTreeMap, HashMap};
+use std::slice;
+use std::sync::Arc;
 use json_flex::JFObject;
+use serde::de::{self, Deserializer, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::de::value::MapDeserializer;
+use serde_json;
+use serde_json::Value;
+use error::Error;
+use groonga_value::GroongaValue;
+use load_result::LoadResult;
+use response_header::ResponseHeader;
+use schema::LiveSchema;
+
+/// Column definition in select response, such as `["_key","ShortText"]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnDef {
+    name: String,
+    value_type: String,
+}
+
+impl ColumnDef {
+    /// Return column name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return Groonga's type name, such as `ShortText`.
+    pub fn value_type(&self) -> &str {
+        &self.value_type
+    }
+}
+
+/// A record whose values are converted by column types.
+///
+/// This type is generated by
+/// [`ResultParser#typed_records()`](struct.ResultParser.html#method.typed_records).
+#[derive(Clone, Debug)]
+pub struct TypedRecord {
+    column_defs: Arc<Vec<ColumnDef>>,
+    values: Vec<GroongaValue>,
+}
+
+impl TypedRecord {
+    /// Get value by column name.
+    pub fn get(&self, name: &str) -> Option<&GroongaValue> {
+        self.column_defs
+            .iter()
+            .position(|def| def.name == name)
+            .and_then(|index| self.values.get(index))
+    }
+
+    /// Return column definitions.
+    pub fn column_defs(&self) -> &[ColumnDef] {
+        &self.column_defs
+    }
+
+    /// Return values in column definitions order.
+    pub fn values(&self) -> &[GroongaValue] {
+        &self.values
+    }
+
+    /// Convert into values in column definitions order.
+    pub fn into_values(self) -> Vec<GroongaValue> {
+        self.values
+    }
+}
+
+/// A set of records in select response, such as search result and drilldown.
+///
+/// A set of records is `[[total_hits], [column_defs...], records...]`.
+#[derive(Clone, Debug)]
+pub struct ResultSet<'a> {
+    total_hits: i64,
+    column_defs: Vec<ColumnDef>,
+    records: &'a [Value],
+}
+
+impl<'a> ResultSet<'a> {
+    fn parse(result: &'a Value) -> Result<ResultSet<'a>, Error> {
+        let result = try!(result.as_array()
+            .ok_or_else(|| Error::Json("result is not array".to_owned())));
+        let total_hits = try!(result.get(0)
+            .and_then(|hits| hits.get(0))
+            .and_then(|hits| hits.as_i64())
+            .ok_or_else(|| Error::Json("result does not have total hits".to_owned())));
+        let column_defs = try!(result.get(1)
+            .ok_or_else(|| Error::Json("result does not have column definitions".to_owned()))
+            .and_then(parse_column_defs));
+        let records = if result.len() > 2 {
+            &result[2..]
+        } else {
+            &[]
+        };
+        Ok(ResultSet {
+            total_hits: total_hits,
+            column_defs: column_defs,
+            records: records,
+        })
+    }
+
+    /// Return a number of hit records.
+    pub fn total_hits(&self) -> i64 {
+        self.total_hits
+    }
+
+    /// Return column definitions.
+    pub fn column_defs(&self) -> &[ColumnDef] {
+        &self.column_defs
+    }
+
+    /// Return a number of records in this set.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Return whether this set has no records.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Deserialize records into user-defined type by column names.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<Vec<T>, Error> {
+        let names = self.column_defs.iter().map(|def| def.name.clone()).collect::<Vec<_>>();
+        self.records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| deserialize_record(&names, index, record))
+            .collect()
+    }
+
+    /// Convert records into `GroongaValue` by column types.
+    pub fn typed_records(&self) -> Result<Vec<TypedRecord>, Error> {
+        let column_defs = Arc::new(self.column_defs.clone());
+        self.records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| {
+                let values = try!(record.as_array()
+                    .ok_or_else(|| Error::Json(format!("record {} is not array", index))));
+                if values.len() != column_defs.len() {
+                    return Err(Error::Json(format!("record {} has {} values but {} columns are \
+                                                    defined",
+                                                   index,
+                                                   values.len(),
+                                                   column_defs.len())));
+                }
+                let values = try!(column_defs.iter()
+                    .zip(values.iter())
+                    .map(|(def, value)| {
+                        GroongaValue::from_json(&def.value_type, value).map_err(|err| {
+                            Error::Deserialize(format!("record {}: column `{}`: {}",
+                                                       index,
+                                                       def.name,
+                                                       err))
+                        })
+                    })
+                    .collect());
+                Ok(TypedRecord {
+                    column_defs: column_defs.clone(),
+                    values: values,
+                })
+            })
+            .collect()
+    }
+}
 
 #[derive(Clone)]
 pub struct Rows {
-    data: Option<Vec<JFObject>>,
+    values: Vec<JFObject>,
+    column_defs: Vec<ColumnDef>,
 }
 
 /// A result rows representing type.
 /// This type is usually generated by
 /// [`ResultParser#into_raw()`](struct.ResultParser.html#method.into_row).
+///
+/// Select result is `[[total_hits], [column_defs...], records...]`.
 impl Rows {
+    /// Create `Rows` from select result.
+    ///
+    /// A malformed column definition is kept as a column with empty name
+    /// and type, so that values of later columns keep their positions.
     pub fn new(data: Option<Vec<JFObject>>) -> Rows {
-        Rows { data: data }
+        let values = match data.and_then(|mut data| data.pop()) {
+            Some(JFObject::Array(values)) => values,
+            _ => Vec::new(),
+        };
+        let column_defs = values.get(1)
+            .and_then(|defs| defs.into_vec())
+            .map(|defs| {
+                defs.iter()
+                    .map(|def| {
+                        to_column_def(def).unwrap_or_else(|| {
+                            ColumnDef {
+                                name: String::new(),
+                                value_type: String::new(),
+                            }
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_else(Vec::new);
+        Rows {
+            values: values,
+            column_defs: column_defs,
+        }
     }
 
     /// Get columns in `Rows`.
+    ///
+    /// It contains total hits, column definitions and records.
+    /// Use `total_hits`, `column_defs` and `records` instead of indexing.
     pub fn columns(&mut self) -> Option<Vec<JFObject>> {
-        let popable = match self.data.clone() {
-            Some(v) => v,
-            None => return None,
-        };
-        let pop = match popable.clone().pop() {
-            Some(v) => v,
-            None => return None,
+        if self.values.is_empty() {
+            None
+        } else {
+            Some(self.values.clone())
+        }
+    }
+
+    /// Return a number of hit records.
+    ///
+    /// It may be larger than a number of records because of `limit`.
+    pub fn total_hits(&self) -> Option<i64> {
+        self.values
+            .get(0)
+            .and_then(|hits| hits.into_vec())
+            .and_then(|hits| hits.get(0))
+            .and_then(|hits| hits.into_i64())
+            .cloned()
+    }
+
+    /// Return column definitions.
+    pub fn column_defs(&self) -> &[ColumnDef] {
+        &self.column_defs
+    }
+
+    /// Return an iterator over records.
+    pub fn records(&self) -> Records {
+        let records = if self.values.len() > 2 {
+            &self.values[2..]
+        } else {
+            &[]
         };
-        match pop.into_vec().clone() {
-            Some(v) => Some(v.clone()),
-            None => None,
+        Records {
+            column_defs: &self.column_defs,
+            iter: records.iter(),
         }
     }
 }
 
+/// An iterator over records in `Rows`.
+pub struct Records<'a> {
+    column_defs: &'a [ColumnDef],
+    iter: slice::Iter<'a, JFObject>,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Record<'a>;
+
+    fn next(&mut self) -> Option<Record<'a>> {
+        self.iter.next().map(|record| {
+            Record {
+                column_defs: self.column_defs,
+                values: record.into_vec().map(|values| values.as_slice()).unwrap_or(&[]),
+            }
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for Records<'a> {}
+
+/// A record in `Rows`.
+#[derive(Clone, Copy, Debug)]
+pub struct Record<'a> {
+    column_defs: &'a [ColumnDef],
+    values: &'a [JFObject],
+}
+
+impl<'a> Record<'a> {
+    /// Get value by column name.
+    pub fn get(&self, name: &str) -> Option<&'a JFObject> {
+        self.column_defs
+            .iter()
+            .position(|def| def.name == name)
+            .and_then(|index| self.values.get(index))
+    }
+
+    /// Return values in column definitions order.
+    pub fn values(&self) -> &'a [JFObject] {
+        self.values
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ResultParser {
-    result: Box<JFObject>,
+    result: Value,
 }
 
 /// ResultParser
@@ -47,103 +313,458 @@ pub struct ResultParser {
 ///
 /// error response:
 /// `[[status, start_time, elapsed_time, error_information, ...]]`
+///
+/// With `command_version=3`, Groonga returns `{"header": {...}, "body": ...}` instead.
 impl ResultParser {
+    /// Parse response json.
+    ///
+    /// This method returns `Error::Json` if response is not json
+    /// or it does not have Groonga's response header.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ruroonga_client::ResultParser;
+    ///
+    /// let result = ResultParser::parse("[[0,1452348610.39281,0.000101566314697266],true]");
+    /// assert_eq!(0, result.unwrap().status().unwrap());
+    /// assert!(ResultParser::parse("<html>502 Bad Gateway</html>").is_err());
+    /// ```
+    pub fn parse(json: &str) -> Result<ResultParser, Error> {
+        let result = try!(serde_json::from_str(json)
+            .map_err(|err| Error::Json(format!("{}", err))));
+        let parser = ResultParser { result: result };
+        try!(parser.header_value());
+        Ok(parser)
+    }
+
+    /// Create ResultParser from response json.
+    ///
+    /// # Panics
+    ///
+    /// Panics if response json is corrupted. Use `ResultParser::parse` instead.
     pub fn new(json: String) -> ResultParser {
-        ResultParser { result: json_flex::decode(json) }
+        match ResultParser::parse(&json) {
+            Ok(parser) => parser,
+            Err(err) => panic!("{}", err),
+        }
     }
 
     /// Get raw response result.
-    pub fn get_raw_object(&mut self) -> Box<JFObject> {
-        self.result.clone()
+    pub fn get_raw_object(&self) -> Box<JFObject> {
+        Box::new(to_jf_object(&self.result))
+    }
+
+    // Return header array, or header object with `command_version=3`.
+    fn header_value(&self) -> Result<&Value, Error> {
+        let header = match self.result {
+            Value::Array(ref result) => result.get(0),
+            Value::Object(ref result) => result.get("header"),
+            _ => None,
+        };
+        match header {
+            Some(header) if header.is_object() ||
+                            header.as_array().map_or(false, |header| !header.is_empty()) => {
+                Ok(header)
+            }
+            _ => Err(Error::Json("response does not have header".to_owned())),
+        }
+    }
+
+    fn header_element(&self, index: usize, key: &str, name: &str) -> Result<&Value, Error> {
+        let header = try!(self.header_value());
+        let element = match *header {
+            Value::Object(ref header) => header.get(key),
+            _ => header.get(index),
+        };
+        element.ok_or_else(|| Error::Json(format!("header does not have {}", name)))
     }
 
     /// Return header elements in response.
+    pub fn get_header(&self) -> Result<JFObject, Error> {
+        self.header_value().map(to_jf_object)
+    }
+
+    /// Return typed header in response.
     ///
-    /// # Panics
+    /// # Examples
+    ///
+    /// ```
+    /// use ruroonga_client::{ResultParser, ReturnCode};
     ///
-    /// Panics if response json is corrupted.
-    pub fn get_header(&mut self) -> JFObject {
-        self.result[0].clone()
+    /// let result = ResultParser::parse(r#"[[-63,1452348610.39281,0.0001,"syntax error"]]"#)
+    ///     .unwrap();
+    /// let header = result.header().unwrap();
+    /// assert_eq!(ReturnCode::SyntaxError, header.return_code());
+    /// assert_eq!(Some("syntax error"), header.error_message());
+    /// ```
+    pub fn header(&self) -> Result<ResponseHeader, Error> {
+        self.header_value().and_then(ResponseHeader::from_value)
     }
 
     /// Return status in response.
-    ///
-    /// # Panics
-    ///
-    /// Panics if response json is corrupted.
-    pub fn status(&mut self) -> Option<&i64> {
-        self.result[0][0].into_i64()
+    pub fn status(&self) -> Result<i64, Error> {
+        try!(self.header_element(0, "return_code", "status"))
+            .as_i64()
+            .ok_or_else(|| Error::Json("status is not integer".to_owned()))
     }
 
     /// Return start time in response.
-    ///
-    /// # Panics
-    ///
-    /// Panics if response json is corrupted.
-    pub fn start_time(&mut self) -> Option<&f64> {
-        self.result[0][1].into_f64()
+    pub fn start_time(&self) -> Result<f64, Error> {
+        try!(self.header_element(1, "start_time", "start time"))
+            .as_f64()
+            .ok_or_else(|| Error::Json("start time is not number".to_owned()))
     }
 
     /// Return elapsed time in response.
-    ///
-    /// # Panics
-    ///
-    /// Panics if response json is corrupted.
-    pub fn elapsed_time(&mut self) -> Option<&f64> {
-        self.result[0][2].into_f64()
+    pub fn elapsed_time(&self) -> Result<f64, Error> {
+        try!(self.header_element(2, "elapsed_time", "elapsed time"))
+            .as_f64()
+            .ok_or_else(|| Error::Json("elapsed time is not number".to_owned()))
     }
 
-    /// [nodoc]
-    #[inline]
-    fn matched_columns_num(&mut self) -> Option<i64> {
-        let vectoizable = match self.result[1][0][0].into_vec().clone() {
-            Some(elem) => elem,
-            None => return None,
-        };
-        let pop = match vectoizable.clone().pop() {
-            Some(pop) => pop,
-            None => return None,
-        };
-        match pop.into_i64().clone() {
-            Some(v) => Some(v.clone()),
-            None => None,
+    /// Return `Error::Groonga` if response is an error response.
+    pub fn check_status(&self) -> Result<(), Error> {
+        let header = try!(self.header());
+        if header.is_success() {
+            return Ok(());
         }
+        Err(Error::Groonga {
+            code: header.return_code(),
+            message: header.error_message().unwrap_or("").to_owned(),
+        })
+    }
+
+    // Return body element in success response.
+    fn body(&self) -> Result<&Value, Error> {
+        try!(self.check_status());
+        self.raw_body()
+    }
+
+    // Return body element regardless of return code.
+    fn raw_body(&self) -> Result<&Value, Error> {
+        let body = match self.result {
+            Value::Object(ref result) => result.get("body"),
+            _ => self.result.get(1),
+        };
+        body.ok_or_else(|| Error::Json("response does not have body".to_owned()))
     }
 
     /// Return a number of matched columns in response.
-    pub fn matched_columns(&mut self) -> Option<i64> {
-        match self.status() {
-            Some(&0) => self.matched_columns_num(),
-            Some(_) => None,
-            None => None,
-        }
+    pub fn matched_columns(&self) -> Result<i64, Error> {
+        try!(self.body())
+            .get(0)
+            .and_then(|result| result.get(0))
+            .and_then(|count| count.get(0))
+            .and_then(|count| count.as_i64())
+            .ok_or_else(|| Error::Json("response does not have matched columns".to_owned()))
     }
 
     /// Get result in response.
     ///
     /// If request succeeded, it can get matched result array.
     /// Otherwise, one can get error messages.
+    pub fn result(&self) -> Result<Vec<JFObject>, Error> {
+        match try!(self.status()) {
+            0 => {
+                try!(self.body())
+                    .get(0)
+                    .map(|result| vec![to_jf_object(result)])
+                    .ok_or_else(|| Error::Json("response does not have result".to_owned()))
+            }
+            _ => {
+                let header = try!(self.header());
+                header.error_message()
+                    .map(|message| vec![JFObject::String(message.to_owned())])
+                    .ok_or_else(|| Error::Json("header does not have error message".to_owned()))
+            }
+        }
+    }
+
+    /// Deserialize records in select response into user-defined type.
     ///
-    /// # Panics
+    /// Each record is mapped to `T` by column names in column definitions,
+    /// so it does not depend on the order of `output_columns`.
+    ///
+    /// # Examples
     ///
-    /// Panics if response json is corrupted.
-    pub fn result(&mut self) -> Option<Vec<JFObject>> {
-        match self.status() {
-            Some(&0) => Some(vec![self.result[1][0].clone()]),
-            Some(_) => Some(vec![self.result[0][3].clone()]),
-            None => None,
+    /// ```
+    /// extern crate ruroonga_client;
+    /// #[macro_use]
+    /// extern crate serde_derive;
+    ///
+    /// use ruroonga_client::ResultParser;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Site {
+    ///     #[serde(rename = "_key")]
+    ///     key: String,
+    ///     title: String,
+    /// }
+    ///
+    /// # fn main() {
+    /// let result = ResultParser::parse(r#"[[0,1452348610.39281,0.0001],
+    ///     [[[1],[["title","ShortText"],["_key","ShortText"]],
+    ///       ["test record","http://example.org/"]]]]"#).unwrap();
+    /// let sites: Vec<Site> = result.deserialize().unwrap();
+    /// assert_eq!("http://example.org/", sites[0].key);
+    /// assert_eq!("test record", sites[0].title);
+    /// # }
+    /// ```
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<Vec<T>, Error> {
+        try!(self.result_set()).deserialize()
+    }
+
+    /// Convert records in select response into `GroongaValue` by column types.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ruroonga_client::{GroongaValue, ResultParser};
+    ///
+    /// let result = ResultParser::parse(r#"[[0,1452348610.39281,0.0001],
+    ///     [[[1],[["_id","UInt32"],["tags","ShortText"]],[1,["groonga","rust"]]]]]"#)
+    ///     .unwrap();
+    /// let records = result.typed_records().unwrap();
+    /// assert_eq!(Some(&GroongaValue::UInt(1)), records[0].get("_id"));
+    /// ```
+    pub fn typed_records(&self) -> Result<Vec<TypedRecord>, Error> {
+        try!(self.result_set()).typed_records()
+    }
+
+    /// Return search result in select response.
+    pub fn result_set(&self) -> Result<ResultSet, Error> {
+        try!(self.body())
+            .get(0)
+            .ok_or_else(|| Error::Json("response does not have result".to_owned()))
+            .and_then(ResultSet::parse)
+    }
+
+    /// Return results of `drilldown` in the order of its keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ruroonga_client::ResultParser;
+    ///
+    /// let result = ResultParser::parse(r#"[[0,1452348610.39281,0.0001],
+    ///     [[[1],[["_key","ShortText"]],["groonga"]],
+    ///      [[1],[["_key","ShortText"],["_nsubrecs","Int32"]],["Groonga",1]]]]"#)
+    ///     .unwrap();
+    /// let drilldowns = result.drilldowns().unwrap();
+    /// assert_eq!(1, drilldowns[0].total_hits());
+    /// ```
+    pub fn drilldowns(&self) -> Result<Vec<ResultSet>, Error> {
+        let body = try!(try!(self.body())
+            .as_array()
+            .ok_or_else(|| Error::Json("response body is not array".to_owned())));
+        body.iter()
+            .skip(1)
+            .filter(|drilldown| drilldown.is_array())
+            .map(ResultSet::parse)
+            .collect()
+    }
+
+    /// Return results of labeled drilldowns, a.k.a. `drilldowns[label]`.
+    pub fn labeled_drilldowns(&self) -> Result<BTreeMap<&str, ResultSet>, Error> {
+        let body = try!(try!(self.body())
+            .as_array()
+            .ok_or_else(|| Error::Json("response body is not array".to_owned())));
+        let mut drilldowns = BTreeMap::new();
+        for labeled in body.iter().skip(1).filter_map(|drilldown| drilldown.as_object()) {
+            for (label, drilldown) in labeled {
+                let drilldown = try!(ResultSet::parse(drilldown)
+                    .map_err(|err| Error::Json(format!("drilldown `{}`: {}", label, err))));
+                drilldowns.insert(label.as_str(), drilldown);
+            }
+        }
+        Ok(drilldowns)
+    }
+
+    /// Return result of `load` command.
+    ///
+    /// Unlike other methods, this method returns the result even if Groonga
+    /// reports an error because some records are not loaded with `output_errors`.
+    pub fn load_result(&self) -> Result<LoadResult, Error> {
+        let has_errors = self.raw_body()
+            .ok()
+            .map_or(false, |body| body.get("errors").is_some());
+        if !has_errors {
+            try!(self.check_status());
         }
+        self.raw_body().and_then(LoadResult::from_body)
+    }
+
+    /// Return tables and columns in response of `schema` command.
+    pub fn schema(&self) -> Result<LiveSchema, Error> {
+        self.body().and_then(LiveSchema::from_body)
     }
 
     /// Convert to `Rows` type and return its type values.
-    pub fn into_row(&mut self) -> Rows {
-        Rows::new(self.result())
+    ///
+    /// This method returns `Error::Protocol` if a column definition is malformed.
+    pub fn into_row(&self) -> Result<Rows, Error> {
+        let rows = Rows::new(Some(try!(self.result())));
+        if let Some(index) = rows.column_defs.iter().position(|def| def.name.is_empty()) {
+            return Err(Error::Protocol(format!("column definition {} is malformed", index)));
+        }
+        Ok(rows)
+    }
+}
+
+fn to_column_def(definition: &JFObject) -> Option<ColumnDef> {
+    let definition = match definition.into_vec() {
+        Some(definition) => definition,
+        None => return None,
+    };
+    match (definition.get(0).and_then(|name| name.into_string()),
+           definition.get(1).and_then(|value_type| value_type.into_string())) {
+        (Some(name), Some(value_type)) => {
+            Some(ColumnDef {
+                name: name.clone(),
+                value_type: value_type.clone(),
+            })
+        }
+        _ => None,
+    }
+}
+
+// Column definitions are `[["name", "type"], ...]`.
+fn parse_column_defs(definitions: &Value) -> Result<Vec<ColumnDef>, Error> {
+    let definitions = try!(definitions.as_array()
+        .ok_or_else(|| Error::Json("column definitions are not array".to_owned())));
+    definitions.iter()
+        .map(|definition| {
+            match (definition.get(0).and_then(|name| name.as_str()),
+                   definition.get(1).and_then(|value_type| value_type.as_str())) {
+                (Some(name), Some(value_type)) => {
+                    Ok(ColumnDef {
+                        name: name.to_owned(),
+                        value_type: value_type.to_owned(),
+                    })
+                }
+                _ => Err(Error::Json("column definition does not have name and type".to_owned())),
+            }
+        })
+        .collect()
+}
+
+fn deserialize_record<T: DeserializeOwned>(names: &[String],
+                                           index: usize,
+                                           record: &Value)
+                                           -> Result<T, Error> {
+    let values = try!(record.as_array()
+        .ok_or_else(|| Error::Json(format!("record {} is not array", index))));
+    if values.len() != names.len() {
+        return Err(Error::Json(format!("record {} has {} values but {} columns are defined",
+                                       index,
+                                       values.len(),
+                                       names.len())));
+    }
+    let columns = names.iter()
+        .cloned()
+        .zip(values.iter().cloned())
+        .map(|(name, value)| {
+            (name.clone(),
+             ColumnValue {
+                 name: name,
+                 value: value,
+             })
+        });
+    T::deserialize(MapDeserializer::new(columns))
+        .map_err(|err: serde_json::Error| Error::Deserialize(format!("record {}: {}", index, err)))
+}
+
+// Column value which reports its column name on deserialize error.
+struct ColumnValue {
+    name: String,
+    value: Value,
+}
+
+macro_rules! forward_with_column {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V)
+                                        -> Result<V::Value, serde_json::Error> {
+                let name = self.name;
+                self.value
+                    .$method($($arg,)* visitor)
+                    .map_err(|err| de::Error::custom(format!("column `{}`: {}", name, err)))
+            }
+        )*
+    }
+}
+
+impl<'de> Deserializer<'de> for ColumnValue {
+    type Error = serde_json::Error;
+
+    forward_with_column! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+}
+
+impl<'de> IntoDeserializer<'de, serde_json::Error> for ColumnValue {
+    type Deserializer = ColumnValue;
+
+    fn into_deserializer(self) -> ColumnValue {
+        self
+    }
+}
+
+// Convert into json_flex object to keep compatibility.
+fn to_jf_object(value: &Value) -> JFObject {
+    match *value {
+        Value::Null => JFObject::Null,
+        Value::Bool(true) => JFObject::True,
+        Value::Bool(false) => JFObject::False,
+        Value::Number(ref number) => {
+            match number.as_i64() {
+                Some(v) => JFObject::Integer(v),
+                None => JFObject::Float(number.as_f64().unwrap_or(0.0)),
+            }
+        }
+        Value::String(ref v) => JFObject::String(v.clone()),
+        Value::Array(ref vec) => JFObject::Array(vec.iter().map(to_jf_object).collect()),
+        Value::Object(ref map) => {
+            JFObject::Dictionary(map.iter()
+                .map(|(k, v)| (k.clone(), to_jf_object(v)))
+                .collect::<HashMap<String, JFObject>>())
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use return_code::ReturnCode;
 
     const RESPONSE: &'static str = r#"
     [[0,1452348610.39281,0.000101566314697266],
@@ -159,12 +780,16 @@ mod tests {
      [8,"http://example.org/gat","test test record eight."],
      [9,"http://example.com/vdw","test test record nine."]]]]"#;
 
+    const ERROR_RESPONSE: &'static str = r#"
+    [[-22,1337566253.89858,0.000355720520019531,"invalid table name: <Nonexistent>",
+      [["grn_select","proc.c",897]]]]"#;
+
     #[test]
     fn parse_result() {
-        let mut decode = ResultParser::new(RESPONSE.to_string());
-        assert_eq!(&0, decode.status().unwrap());
-        assert_eq!(&1452348610.39281, decode.start_time().unwrap());
-        assert_eq!(&0.000101566314697266, decode.elapsed_time().unwrap());
+        let decode = ResultParser::parse(RESPONSE).unwrap();
+        assert_eq!(0, decode.status().unwrap());
+        assert_eq!(1452348610.39281, decode.start_time().unwrap());
+        assert_eq!(0.000101566314697266, decode.elapsed_time().unwrap());
         assert_eq!(9, decode.matched_columns().unwrap());
         let vec = decode.result().unwrap().pop().unwrap().unwrap_vec().clone();
         let expected = concat!(r#"Array([Integer(1), "#,
@@ -176,12 +801,324 @@ mod tests {
 
     #[test]
     fn row_columns() {
-        let mut decode = ResultParser::new(RESPONSE.to_string());
-        let vec = decode.into_row().columns().unwrap();
+        let decode = ResultParser::new(RESPONSE.to_string());
+        let vec = decode.into_row().unwrap().columns().unwrap();
         let expected = concat!(r#"Array([Integer(1), "#,
                                r#"String("http://example.org/"), "#,
                                r#"String("This is test record 1!")])"#)
                 .to_owned();
         assert_eq!(expected, format!("{:?}", vec[2]))
     }
+
+    #[test]
+    fn rows_records() {
+        let rows = ResultParser::parse(RESPONSE).unwrap().into_row().unwrap();
+        assert_eq!(Some(9), rows.total_hits());
+        let names = rows.column_defs().iter().map(|def| def.name()).collect::<Vec<_>>();
+        assert_eq!(vec!["_id", "_key", "title"], names);
+        assert_eq!("UInt32", rows.column_defs()[0].value_type());
+        assert_eq!(9, rows.records().len());
+        let record = rows.records().nth(1).unwrap();
+        assert_eq!(2, *record.get("_id").unwrap().unwrap_i64());
+        assert_eq!("test record 2.", record.get("title").unwrap().unwrap_string());
+        assert!(record.get("nonexistent").is_none());
+        assert_eq!(3, record.values().len());
+    }
+
+    #[test]
+    fn typed_records() {
+        let decode = ResultParser::parse(RESPONSE).unwrap();
+        let records = decode.typed_records().unwrap();
+        assert_eq!(9, records.len());
+        assert_eq!(Some(&GroongaValue::UInt(1)), records[0].get("_id"));
+        assert_eq!(Some(&GroongaValue::Text("http://example.org/".to_owned())),
+                   records[0].get("_key"));
+        assert_eq!(3, records[0].values().len());
+        assert_eq!("title", records[0].column_defs()[2].name());
+    }
+
+    #[test]
+    fn typed_records_with_mismatched_type() {
+        let decode = ResultParser::parse(r#"
+        [[0,1452348610.39281,0.000101566314697266],
+         [[[1],[["_id","UInt32"],["_key","ShortText"]],[1,2]]]]"#)
+            .unwrap();
+        match decode.typed_records() {
+            Err(Error::Deserialize(message)) => {
+                assert!(message.starts_with("record 0: column `_key`: "))
+            }
+            _ => panic!("mismatched type should not be converted"),
+        }
+    }
+
+    #[test]
+    fn typed_records_with_mismatched_length() {
+        let decode = ResultParser::parse(r#"
+        [[0,1452348610.39281,0.000101566314697266],
+         [[[1],[["_id","UInt32"],["_key","ShortText"]],[1]]]]"#)
+            .unwrap();
+        match decode.typed_records() {
+            Err(Error::Json(message)) => {
+                assert_eq!("record 0 has 1 values but 2 columns are defined", message)
+            }
+            _ => panic!("short record should not be converted"),
+        }
+    }
+
+    #[test]
+    fn send_typed_records_to_thread() {
+        let records = ResultParser::parse(RESPONSE).unwrap().typed_records().unwrap();
+        let handle = ::std::thread::spawn(move || records.len());
+        assert_eq!(9, handle.join().unwrap());
+    }
+
+    const DRILLDOWN_RESPONSE: &'static str = r#"
+    [[0,1452348610.39281,0.000101566314697266],
+     [[[2],[["_key","ShortText"]],["http://example.org/"],["http://example.net/"]],
+      [[2],[["_key","ShortText"],["_nsubrecs","Int32"]],["Groonga",2],["Rust",1]],
+      [[1],[["_key","ShortText"],["_nsubrecs","Int32"]],["cosmo0920",2]]]]"#;
+
+    const LABELED_DRILLDOWN_RESPONSE: &'static str = r#"
+    [[0,1452348610.39281,0.000101566314697266],
+     [[[2],[["_key","ShortText"]],["http://example.org/"],["http://example.net/"]],
+      {"tag":[[2],[["_key","ShortText"],["_nsubrecs","Int32"],["_sum","Int64"]],
+              ["Groonga",2,30],["Rust",1,5]],
+       "author":[[1],[["_key","ShortText"],["_nsubrecs","Int32"]],["cosmo0920",2]]}]]"#;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Tag {
+        #[serde(rename = "_key")]
+        key: String,
+        #[serde(rename = "_nsubrecs")]
+        count: i32,
+    }
+
+    #[test]
+    fn parse_drilldowns() {
+        let decode = ResultParser::parse(DRILLDOWN_RESPONSE).unwrap();
+        assert_eq!(2, decode.result_set().unwrap().len());
+        let drilldowns = decode.drilldowns().unwrap();
+        assert_eq!(2, drilldowns.len());
+        assert_eq!(2, drilldowns[0].total_hits());
+        let tags: Vec<Tag> = drilldowns[0].deserialize().unwrap();
+        assert_eq!(vec![Tag {
+                            key: "Groonga".to_owned(),
+                            count: 2,
+                        },
+                        Tag {
+                            key: "Rust".to_owned(),
+                            count: 1,
+                        }],
+                   tags);
+        let records = drilldowns[1].typed_records().unwrap();
+        assert_eq!(Some(&GroongaValue::Int(2)), records[0].get("_nsubrecs"));
+        assert!(decode.labeled_drilldowns().unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_labeled_drilldowns() {
+        let decode = ResultParser::parse(LABELED_DRILLDOWN_RESPONSE).unwrap();
+        assert_eq!(2, decode.result_set().unwrap().total_hits());
+        assert!(decode.drilldowns().unwrap().is_empty());
+        let drilldowns = decode.labeled_drilldowns().unwrap();
+        assert_eq!(vec!["author", "tag"], drilldowns.keys().cloned().collect::<Vec<_>>());
+        let tag = &drilldowns["tag"];
+        assert_eq!("_sum", tag.column_defs()[2].name());
+        let records = tag.typed_records().unwrap();
+        assert_eq!(Some(&GroongaValue::Int(30)), records[0].get("_sum"));
+        assert_eq!(1, drilldowns["author"].len());
+    }
+
+    #[test]
+    fn rows_with_malformed_column_definition() {
+        let decode = ResultParser::parse(r#"[[0,0.0,0.0],
+            [[[1],[["_id","UInt32"],"broken",["_key","ShortText"]],[1,"x","key"]]]]"#)
+            .unwrap();
+        match decode.into_row() {
+            Err(Error::Protocol(message)) => {
+                assert_eq!("column definition 1 is malformed", message)
+            }
+            _ => panic!("malformed column definition should be rejected"),
+        }
+        let rows = Rows::new(Some(decode.result().unwrap()));
+        let record = rows.records().next().unwrap();
+        assert_eq!(Some("key"),
+                   record.get("_key").and_then(|key| key.into_string()).map(|key| key.as_str()));
+    }
+
+    #[test]
+    fn rows_without_records() {
+        let rows = Rows::new(None);
+        assert_eq!(None, rows.total_hits());
+        assert!(rows.column_defs().is_empty());
+        assert_eq!(0, rows.records().count());
+    }
+
+    #[test]
+    fn end_of_data_is_success() {
+        let decode = ResultParser::parse("[[1,0.0,0.0],true]").unwrap();
+        assert!(decode.check_status().is_ok());
+    }
+
+    #[test]
+    fn parse_error_response() {
+        let decode = ResultParser::parse(ERROR_RESPONSE).unwrap();
+        assert_eq!(-22, decode.status().unwrap());
+        match decode.matched_columns() {
+            Err(Error::Groonga { code, message }) => {
+                assert_eq!(ReturnCode::InvalidArgument, code);
+                assert_eq!("invalid table name: <Nonexistent>", message);
+            }
+            _ => panic!("error response should not have matched columns"),
+        }
+        let message = decode.result().unwrap().pop().unwrap();
+        assert_eq!("invalid table name: <Nonexistent>", message.unwrap_string());
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Site {
+        #[serde(rename = "_id")]
+        id: u32,
+        #[serde(rename = "_key")]
+        key: String,
+        title: String,
+    }
+
+    #[test]
+    fn deserialize_records() {
+        let decode = ResultParser::parse(RESPONSE).unwrap();
+        let sites: Vec<Site> = decode.deserialize().unwrap();
+        assert_eq!(9, sites.len());
+        assert_eq!(Site {
+                       id: 1,
+                       key: "http://example.org/".to_owned(),
+                       title: "This is test record 1!".to_owned(),
+                   },
+                   sites[0]);
+    }
+
+    #[test]
+    fn deserialize_records_in_different_column_order() {
+        let decode = ResultParser::parse(r#"
+        [[0,1452348610.39281,0.000101566314697266],
+         [[[1],
+           [["title","ShortText"],["_key","ShortText"],["_id","UInt32"]],
+           ["This is test record 1!","http://example.org/",1]]]]"#)
+            .unwrap();
+        let sites: Vec<Site> = decode.deserialize().unwrap();
+        assert_eq!("http://example.org/", sites[0].key);
+        assert_eq!("This is test record 1!", sites[0].title);
+    }
+
+    #[test]
+    fn deserialize_missing_column() {
+        let decode = ResultParser::parse(r#"
+        [[0,1452348610.39281,0.000101566314697266],
+         [[[1],[["_id","UInt32"],["_key","ShortText"]],[1,"http://example.org/"]]]]"#)
+            .unwrap();
+        match decode.deserialize::<Site>() {
+            Err(Error::Deserialize(message)) => {
+                assert_eq!("record 0: missing field `title`", message)
+            }
+            _ => panic!("missing column should not be deserialized"),
+        }
+    }
+
+    #[test]
+    fn deserialize_type_mismatch() {
+        let decode = ResultParser::parse(r#"
+        [[0,1452348610.39281,0.000101566314697266],
+         [[[1],[["_id","ShortText"],["_key","ShortText"],["title","ShortText"]],
+           ["one","http://example.org/","test"]]]]"#)
+            .unwrap();
+        match decode.deserialize::<Site>() {
+            Err(Error::Deserialize(message)) => {
+                assert_eq!("record 0: column `_id`: invalid type: string \"one\", expected u32",
+                           message)
+            }
+            _ => panic!("mismatched type should not be deserialized"),
+        }
+    }
+
+    #[test]
+    fn deserialize_error_response() {
+        let decode = ResultParser::parse(ERROR_RESPONSE).unwrap();
+        match decode.deserialize::<Site>() {
+            Err(Error::Groonga { .. }) => (),
+            _ => panic!("error response should not be deserialized"),
+        }
+    }
+
+    #[test]
+    fn parse_command_version_3_response() {
+        let decode = ResultParser::parse(r#"
+        {"header":{"return_code":0,"start_time":1452348610.39281,"elapsed_time":0.0001},
+         "body":{"n_loaded_records":1}}"#)
+            .unwrap();
+        assert_eq!(0, decode.status().unwrap());
+        assert_eq!(1452348610.39281, decode.start_time().unwrap());
+        assert_eq!(1, decode.load_result().unwrap().n_loaded_records());
+    }
+
+    #[test]
+    fn load_result_with_errors() {
+        let decode = ResultParser::parse(r#"
+        {"header":{"return_code":-22,"start_time":1452348610.39281,"elapsed_time":0.0001,
+                   "error":{"message":"failed to cast"}},
+         "body":{"n_loaded_records":0,"loaded_ids":[0],
+                 "errors":[{"return_code":-22,"message":"failed to cast"}]}}"#)
+            .unwrap();
+        assert!(decode.check_status().is_err());
+        let result = decode.load_result().unwrap();
+        assert_eq!(Some(&[0][..]), result.loaded_ids());
+        assert_eq!(0, result.errors()[0].index());
+    }
+
+    #[test]
+    fn load_result_of_error_response() {
+        let decode = ResultParser::parse(ERROR_RESPONSE).unwrap();
+        match decode.load_result() {
+            Err(Error::Groonga { .. }) => (),
+            _ => panic!("error response should not have load result"),
+        }
+    }
+
+    #[test]
+    fn parse_truncated_body() {
+        match ResultParser::parse(&RESPONSE[..100]) {
+            Err(Error::Json(_)) => (),
+            _ => panic!("truncated body should not be parsed"),
+        }
+    }
+
+    #[test]
+    fn parse_empty_body() {
+        match ResultParser::parse("") {
+            Err(Error::Json(_)) => (),
+            _ => panic!("empty body should not be parsed"),
+        }
+    }
+
+    #[test]
+    fn parse_non_json_body() {
+        match ResultParser::parse("<html><body>502 Bad Gateway</body></html>") {
+            Err(Error::Json(_)) => (),
+            _ => panic!("non-JSON body should not be parsed"),
+        }
+    }
+
+    #[test]
+    fn parse_json_without_header() {
+        match ResultParser::parse(r#"{"message":"not groonga"}"#) {
+            Err(Error::Json(_)) => (),
+            _ => panic!("JSON without header should not be parsed"),
+        }
+    }
+
+    #[test]
+    fn corrupted_body_element() {
+        let decode = ResultParser::parse("[[0,1452348610.39281,0.000101566314697266]]").unwrap();
+        assert!(decode.matched_columns().is_err());
+        assert!(decode.result().is_err());
+    }
 }
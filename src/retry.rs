@@ -0,0 +1,391 @@
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Information about a retry which is passed to `RetryPolicy::on_retry` hook.
+#[derive(Debug)]
+pub struct RetryEvent {
+    attempt: u32,
+    delay: Duration,
+    kind: io::ErrorKind,
+    error: String,
+}
+
+impl RetryEvent {
+    /// Return the number of the failed attempt. The first attempt is `1`.
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Return how long it waits before the next attempt.
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Return the kind of I/O error which caused this retry.
+    pub fn kind(&self) -> io::ErrorKind {
+        self.kind
+    }
+
+    /// Return the error message which caused this retry.
+    pub fn error(&self) -> &str {
+        &self.error
+    }
+}
+
+/// Retry policy with exponential backoff for `HTTPRequest` and `GQTPRequest`.
+///
+/// By default, only idempotent commands are retried.
+/// Thus, `load` and other commands which modify database are not replayed
+/// unless `idempotent_only(false)` is specified.
+///
+/// # Examples
+///
+/// ```
+/// extern crate ruroonga_client as groonga;
+///
+/// use std::time::Duration;
+/// use groonga::RetryPolicy;
+///
+/// let policy = RetryPolicy::new()
+///   .max_attempts(5)
+///   .backoff(Duration::from_millis(50), Duration::from_secs(2))
+///   .on_retry(|event| println!("retry #{}: {}", event.attempt(), event.error()));
+/// groonga::HTTPRequest::new()
+///   .retry(policy);
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: u32,
+    jitter: bool,
+    idempotent_only: bool,
+    retry_if: Arc<Fn(io::ErrorKind) -> bool + Send + Sync>,
+    on_retry: Option<Arc<Fn(&RetryEvent) + Send + Sync>>,
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("multiplier", &self.multiplier)
+            .field("jitter", &self.jitter)
+            .field("idempotent_only", &self.idempotent_only)
+            .finish()
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2,
+            jitter: true,
+            idempotent_only: true,
+            retry_if: Arc::new(is_transient),
+            on_retry: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    ///
+    /// Create RetryPolicy struct.
+    ///
+    /// Default values are:
+    ///
+    ///   max_attempts: 3
+    ///
+    ///   backoff: from 100 milliseconds up to 5 seconds
+    ///
+    ///   multiplier: 2
+    ///
+    ///   jitter: true
+    ///
+    ///   idempotent_only: true
+    ///
+    ///   retryable errors: connection refused/reset/aborted, broken pipe,
+    ///   unexpected EOF and timed out
+    ///
+    pub fn new() -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    /// Set the maximum number of attempts including the first one.
+    pub fn max_attempts(mut self, max_attempts: u32) -> RetryPolicy {
+        self.max_attempts = if max_attempts == 0 { 1 } else { max_attempts };
+        self
+    }
+
+    /// Set the first backoff delay and the upper limit of backoff delay.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> RetryPolicy {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Set the multiplier of backoff delay for each retry.
+    pub fn multiplier(mut self, multiplier: u32) -> RetryPolicy {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Set whether backoff delay is randomized between a half and the full delay.
+    pub fn jitter(mut self, jitter: bool) -> RetryPolicy {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set whether only idempotent commands are retried.
+    pub fn idempotent_only(mut self, idempotent_only: bool) -> RetryPolicy {
+        self.idempotent_only = idempotent_only;
+        self
+    }
+
+    /// Set predicate which decides whether an error is retryable.
+    pub fn retry_if<F>(mut self, retry_if: F) -> RetryPolicy
+        where F: Fn(io::ErrorKind) -> bool + Send + Sync + 'static
+    {
+        self.retry_if = Arc::new(retry_if);
+        self
+    }
+
+    /// Set hook which is called before each retry.
+    pub fn on_retry<F>(mut self, on_retry: F) -> RetryPolicy
+        where F: Fn(&RetryEvent) + Send + Sync + 'static
+    {
+        self.on_retry = Some(Arc::new(on_retry));
+        self
+    }
+
+    /// Return backoff delay after the `attempt`-th failure without jitter.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let mut delay = self.initial_backoff;
+        for _ in 1..attempt {
+            delay = match delay.checked_mul(self.multiplier) {
+                Some(delay) if delay < self.max_backoff => delay,
+                _ => return self.max_backoff,
+            };
+        }
+        if delay > self.max_backoff {
+            self.max_backoff
+        } else {
+            delay
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn run<T, E, F>(&self, idempotent: bool, mut f: F) -> Result<T, E>
+        where F: FnMut() -> Result<T, E>,
+              E: Retryable + fmt::Display
+    {
+        let mut attempt = 1;
+        loop {
+            let err = match f() {
+                Ok(v) => return Ok(v),
+                Err(err) => err,
+            };
+            let kind = match err.io_error_kind() {
+                Some(kind) => kind,
+                None => return Err(err),
+            };
+            if attempt >= self.max_attempts || (self.idempotent_only && !idempotent) ||
+               !(self.retry_if)(kind) {
+                return Err(err);
+            }
+            let delay = self.jittered(self.delay(attempt));
+            if let Some(ref on_retry) = self.on_retry {
+                on_retry(&RetryEvent {
+                              attempt: attempt,
+                              delay: delay,
+                              kind: kind,
+                              error: err.to_string(),
+                          });
+            }
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    fn jittered(&self, delay: Duration) -> Duration {
+        if !self.jitter {
+            return delay;
+        }
+        let nanos = delay.as_secs() * 1_000_000_000 + delay.subsec_nanos() as u64;
+        let half = nanos / 2;
+        let jittered = half + random() % (nanos - half + 1);
+        Duration::new(jittered / 1_000_000_000,
+                      (jittered % 1_000_000_000) as u32)
+    }
+}
+
+// Errors which can be retried expose its I/O error kind.
+#[doc(hidden)]
+pub trait Retryable {
+    fn io_error_kind(&self) -> Option<io::ErrorKind>;
+}
+
+impl Retryable for io::Error {
+    fn io_error_kind(&self) -> Option<io::ErrorKind> {
+        Some(self.kind())
+    }
+}
+
+/// Default predicate of retryable errors.
+pub fn is_transient(kind: io::ErrorKind) -> bool {
+    match kind {
+        io::ErrorKind::ConnectionRefused |
+        io::ErrorKind::ConnectionReset |
+        io::ErrorKind::ConnectionAborted |
+        io::ErrorKind::NotConnected |
+        io::ErrorKind::BrokenPipe |
+        io::ErrorKind::UnexpectedEof |
+        io::ErrorKind::Interrupted |
+        io::ErrorKind::TimedOut => true,
+        _ => false,
+    }
+}
+
+/// Return whether replaying the command is harmless.
+///
+/// Commands which modify database, such as `load`, `delete`,
+/// `*_create` and `*_remove`, are not idempotent.
+pub fn is_idempotent(command: &str) -> bool {
+    match command {
+        "load" | "delete" | "truncate" | "config_set" | "config_delete" | "plugin_register" |
+        "plugin_unregister" | "lock_acquire" | "lock_release" | "lock_clear" => false,
+        _ => {
+            !(command.ends_with("_create") || command.ends_with("_remove") ||
+              command.ends_with("_rename") || command.ends_with("_copy"))
+        }
+    }
+}
+
+// Extract command name from `/d/select.json?table=...` or `select --table ...` form.
+pub fn command_name(command: &str) -> &str {
+    let command = command.trim_left();
+    if command.starts_with('/') {
+        let path = command.split('?').next().unwrap_or("");
+        let name = path.rsplit('/').next().unwrap_or("");
+        name.split('.').next().unwrap_or("")
+    } else {
+        command.split_whitespace().next().unwrap_or("")
+    }
+}
+
+// Tiny xorshift random number generator for jitter.
+fn random() -> u64 {
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() ^ (d.subsec_nanos() as u64) << 20)
+        .unwrap_or(0x2545_f491_4f6c_dd1d);
+    let mut x = seed | 1;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn exponential_backoff() {
+        let policy = RetryPolicy::new().backoff(Duration::from_millis(100), Duration::from_secs(1));
+        assert_eq!(Duration::from_millis(100), policy.delay(1));
+        assert_eq!(Duration::from_millis(200), policy.delay(2));
+        assert_eq!(Duration::from_millis(400), policy.delay(3));
+        assert_eq!(Duration::from_secs(1), policy.delay(10));
+    }
+
+    #[test]
+    fn jitter_within_range() {
+        let policy = RetryPolicy::new();
+        let delay = policy.jittered(Duration::from_millis(100));
+        assert!(delay >= Duration::from_millis(50));
+        assert!(delay <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn idempotent_commands() {
+        assert!(is_idempotent("select"));
+        assert!(is_idempotent("status"));
+        assert!(!is_idempotent("load"));
+        assert!(!is_idempotent("table_create"));
+        assert!(!is_idempotent("column_remove"));
+    }
+
+    #[test]
+    fn extract_command_name() {
+        assert_eq!("select", command_name("/d/select.json?table=Sites"));
+        assert_eq!("status", command_name("/d/status"));
+        assert_eq!("select", command_name("select --table Sites"));
+        assert_eq!("status", command_name("status"));
+    }
+
+    fn failing(kind: io::ErrorKind,
+               failures: u32)
+               -> (Arc<Mutex<u32>>, Box<FnMut() -> Result<u32, io::Error>>) {
+        let count = Arc::new(Mutex::new(0));
+        let counter = count.clone();
+        (count,
+         Box::new(move || {
+            let mut count = counter.lock().unwrap();
+            *count += 1;
+            if *count <= failures {
+                Err(io::Error::new(kind, "failure"))
+            } else {
+                Ok(*count)
+            }
+        }))
+    }
+
+    #[test]
+    fn retry_transient_error() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let observed = events.clone();
+        let policy = RetryPolicy::new()
+            .backoff(Duration::from_millis(1), Duration::from_millis(1))
+            .on_retry(move |event| observed.lock().unwrap().push(event.attempt()));
+        let (count, f) = failing(io::ErrorKind::ConnectionReset, 2);
+        assert_eq!(3, policy.run(true, f).unwrap());
+        assert_eq!(3, *count.lock().unwrap());
+        assert_eq!(vec![1, 2], *events.lock().unwrap());
+    }
+
+    #[test]
+    fn give_up_after_max_attempts() {
+        let policy = RetryPolicy::new()
+            .max_attempts(2)
+            .backoff(Duration::from_millis(1), Duration::from_millis(1));
+        let (count, f) = failing(io::ErrorKind::ConnectionRefused, 5);
+        assert!(policy.run(true, f).is_err());
+        assert_eq!(2, *count.lock().unwrap());
+    }
+
+    #[test]
+    fn dont_retry_non_idempotent_command() {
+        let policy = RetryPolicy::new().backoff(Duration::from_millis(1), Duration::from_millis(1));
+        let (count, f) = failing(io::ErrorKind::ConnectionReset, 1);
+        assert!(policy.run(false, f).is_err());
+        assert_eq!(1, *count.lock().unwrap());
+    }
+
+    #[test]
+    fn dont_retry_permanent_error() {
+        let policy = RetryPolicy::new().backoff(Duration::from_millis(1), Duration::from_millis(1));
+        let (count, f) = failing(io::ErrorKind::InvalidInput, 1);
+        assert!(policy.run(true, f).is_err());
+        assert_eq!(1, *count.lock().unwrap());
+    }
+}
@@ -0,0 +1,204 @@
+use std::fmt;
+
+macro_rules! return_codes {
+    ($($variant:ident = $code:expr, $name:tt, $description:tt;)*) => {
+        /// Groonga's return code, a.k.a. `grn_rc`.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum ReturnCode {
+            $(
+                #[doc = $description]
+                $variant,
+            )*
+            /// Return code which is not known by this crate.
+            Unknown(i64),
+        }
+
+        impl ReturnCode {
+            /// Convert from numeric return code.
+            pub fn from_code(code: i64) -> ReturnCode {
+                $(
+                    if code == $code {
+                        return ReturnCode::$variant;
+                    }
+                )*
+                ReturnCode::Unknown(code)
+            }
+
+            /// Return numeric return code.
+            pub fn code(&self) -> i64 {
+                match *self {
+                    $(ReturnCode::$variant => $code,)*
+                    ReturnCode::Unknown(code) => code,
+                }
+            }
+
+            /// Return the name which is used in Groonga, such as `"INVALID_ARGUMENT"`.
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    $(ReturnCode::$variant => $name,)*
+                    ReturnCode::Unknown(_) => "UNKNOWN",
+                }
+            }
+
+            /// Return human-readable description, such as `"invalid argument"`.
+            pub fn description(&self) -> &'static str {
+                match *self {
+                    $(ReturnCode::$variant => $description,)*
+                    ReturnCode::Unknown(_) => "unknown return code",
+                }
+            }
+        }
+    }
+}
+
+return_codes! {
+    Success = 0, "SUCCESS", "success";
+    EndOfData = 1, "END_OF_DATA", "end of data";
+    UnknownError = -1, "UNKNOWN_ERROR", "unknown error";
+    OperationNotPermitted = -2, "OPERATION_NOT_PERMITTED", "operation not permitted";
+    NoSuchFileOrDirectory = -3, "NO_SUCH_FILE_OR_DIRECTORY", "no such file or directory";
+    NoSuchProcess = -4, "NO_SUCH_PROCESS", "no such process";
+    InterruptedFunctionCall = -5, "INTERRUPTED_FUNCTION_CALL", "interrupted function call";
+    InputOutputError = -6, "INPUT_OUTPUT_ERROR", "input/output error";
+    NoSuchDeviceOrAddress = -7, "NO_SUCH_DEVICE_OR_ADDRESS", "no such device or address";
+    ArgListTooLong = -8, "ARG_LIST_TOO_LONG", "arg list too long";
+    ExecFormatError = -9, "EXEC_FORMAT_ERROR", "exec format error";
+    BadFileDescriptor = -10, "BAD_FILE_DESCRIPTOR", "bad file descriptor";
+    NoChildProcesses = -11, "NO_CHILD_PROCESSES", "no child processes";
+    ResourceTemporarilyUnavailable = -12, "RESOURCE_TEMPORARILY_UNAVAILABLE",
+        "resource temporarily unavailable";
+    NotEnoughSpace = -13, "NOT_ENOUGH_SPACE", "not enough space";
+    PermissionDenied = -14, "PERMISSION_DENIED", "permission denied";
+    BadAddress = -15, "BAD_ADDRESS", "bad address";
+    ResourceBusy = -16, "RESOURCE_BUSY", "resource busy";
+    FileExists = -17, "FILE_EXISTS", "file exists";
+    ImproperLink = -18, "IMPROPER_LINK", "improper link";
+    NoSuchDevice = -19, "NO_SUCH_DEVICE", "no such device";
+    NotADirectory = -20, "NOT_A_DIRECTORY", "not a directory";
+    IsADirectory = -21, "IS_A_DIRECTORY", "is a directory";
+    InvalidArgument = -22, "INVALID_ARGUMENT", "invalid argument";
+    TooManyOpenFilesInSystem = -23, "TOO_MANY_OPEN_FILES_IN_SYSTEM",
+        "too many open files in system";
+    TooManyOpenFiles = -24, "TOO_MANY_OPEN_FILES", "too many open files";
+    InappropriateIOControlOperation = -25, "INAPPROPRIATE_I_O_CONTROL_OPERATION",
+        "inappropriate I/O control operation";
+    FileTooLarge = -26, "FILE_TOO_LARGE", "file too large";
+    NoSpaceLeftOnDevice = -27, "NO_SPACE_LEFT_ON_DEVICE", "no space left on device";
+    InvalidSeek = -28, "INVALID_SEEK", "invalid seek";
+    ReadOnlyFileSystem = -29, "READ_ONLY_FILE_SYSTEM", "read-only file system";
+    TooManyLinks = -30, "TOO_MANY_LINKS", "too many links";
+    BrokenPipe = -31, "BROKEN_PIPE", "broken pipe";
+    DomainError = -32, "DOMAIN_ERROR", "domain error";
+    ResultTooLarge = -33, "RESULT_TOO_LARGE", "result too large";
+    ResourceDeadlockAvoided = -34, "RESOURCE_DEADLOCK_AVOIDED", "resource deadlock avoided";
+    NoMemoryAvailable = -35, "NO_MEMORY_AVAILABLE", "no memory available";
+    FilenameTooLong = -36, "FILENAME_TOO_LONG", "filename too long";
+    NoLocksAvailable = -37, "NO_LOCKS_AVAILABLE", "no locks available";
+    FunctionNotImplemented = -38, "FUNCTION_NOT_IMPLEMENTED", "function not implemented";
+    DirectoryNotEmpty = -39, "DIRECTORY_NOT_EMPTY", "directory not empty";
+    IllegalByteSequence = -40, "ILLEGAL_BYTE_SEQUENCE", "illegal byte sequence";
+    SocketNotInitialized = -41, "SOCKET_NOT_INITIALIZED", "socket not initialized";
+    OperationWouldBlock = -42, "OPERATION_WOULD_BLOCK", "operation would block";
+    AddressIsNotAvailable = -43, "ADDRESS_IS_NOT_AVAILABLE", "address is not available";
+    NetworkIsDown = -44, "NETWORK_IS_DOWN", "network is down";
+    NoBuffer = -45, "NO_BUFFER", "no buffer";
+    SocketIsAlreadyConnected = -46, "SOCKET_IS_ALREADY_CONNECTED",
+        "socket is already connected";
+    SocketIsNotConnected = -47, "SOCKET_IS_NOT_CONNECTED", "socket is not connected";
+    SocketIsAlreadyShutdowned = -48, "SOCKET_IS_ALREADY_SHUTDOWNED",
+        "socket is already shutdowned";
+    OperationTimeout = -49, "OPERATION_TIMEOUT", "operation timeout";
+    ConnectionRefused = -50, "CONNECTION_REFUSED", "connection refused";
+    RangeError = -51, "RANGE_ERROR", "range error";
+    TokenizerError = -52, "TOKENIZER_ERROR", "tokenizer error";
+    FileCorrupt = -53, "FILE_CORRUPT", "file corrupt";
+    InvalidFormat = -54, "INVALID_FORMAT", "invalid format";
+    ObjectCorrupt = -55, "OBJECT_CORRUPT", "object corrupt";
+    TooManySymbolicLinks = -56, "TOO_MANY_SYMBOLIC_LINKS", "too many symbolic links";
+    NotSocket = -57, "NOT_SOCKET", "not socket";
+    OperationNotSupported = -58, "OPERATION_NOT_SUPPORTED", "operation not supported";
+    AddressIsInUse = -59, "ADDRESS_IS_IN_USE", "address is in use";
+    ZlibError = -60, "ZLIB_ERROR", "zlib error";
+    Lz4Error = -61, "LZ4_ERROR", "LZ4 error";
+    StackOverFlow = -62, "STACK_OVER_FLOW", "stack over flow";
+    SyntaxError = -63, "SYNTAX_ERROR", "syntax error";
+    RetryMax = -64, "RETRY_MAX", "retry max";
+    IncompatibleFileFormat = -65, "INCOMPATIBLE_FILE_FORMAT", "incompatible file format";
+    UpdateNotAllowed = -66, "UPDATE_NOT_ALLOWED", "update not allowed";
+    TooSmallOffset = -67, "TOO_SMALL_OFFSET", "too small offset";
+    TooLargeOffset = -68, "TOO_LARGE_OFFSET", "too large offset";
+    TooSmallLimit = -69, "TOO_SMALL_LIMIT", "too small limit";
+    CasError = -70, "CAS_ERROR", "CAS error";
+    UnsupportedCommandVersion = -71, "UNSUPPORTED_COMMAND_VERSION",
+        "unsupported command version";
+    NormalizerError = -72, "NORMALIZER_ERROR", "normalizer error";
+    TokenFilterError = -73, "TOKEN_FILTER_ERROR", "token filter error";
+    CommandError = -74, "COMMAND_ERROR", "command error";
+    PluginError = -75, "PLUGIN_ERROR", "plugin error";
+    ScorerError = -76, "SCORER_ERROR", "scorer error";
+    Cancel = -77, "CANCEL", "cancel";
+    WindowFunctionError = -78, "WINDOW_FUNCTION_ERROR", "window function error";
+    ZstdError = -79, "ZSTD_ERROR", "Zstandard error";
+}
+
+impl ReturnCode {
+    /// Return whether this return code means success.
+    ///
+    /// `END_OF_DATA` is also success because it is not an error but
+    /// the end of output, for both HTTP and GQTP.
+    pub fn is_success(&self) -> bool {
+        match *self {
+            ReturnCode::Success |
+            ReturnCode::EndOfData => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<i64> for ReturnCode {
+    fn from(code: i64) -> ReturnCode {
+        ReturnCode::from_code(code)
+    }
+}
+
+impl fmt::Display for ReturnCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReturnCode::Unknown(code) => write!(f, "unknown return code ({})", code),
+            _ => write!(f, "{} ({})", self.description(), self.code()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_code() {
+        assert_eq!(ReturnCode::Success, ReturnCode::from_code(0));
+        assert_eq!(ReturnCode::InvalidArgument, ReturnCode::from_code(-22));
+        assert_eq!(ReturnCode::SyntaxError, ReturnCode::from(-63));
+        assert_eq!(ReturnCode::Unknown(-1000), ReturnCode::from_code(-1000));
+    }
+
+    #[test]
+    fn to_code() {
+        assert_eq!(-3, ReturnCode::NoSuchFileOrDirectory.code());
+        assert_eq!(-1000, ReturnCode::Unknown(-1000).code());
+    }
+
+    #[test]
+    fn success_codes() {
+        assert!(ReturnCode::Success.is_success());
+        assert!(ReturnCode::EndOfData.is_success());
+        assert!(!ReturnCode::InvalidArgument.is_success());
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!("END_OF_DATA", ReturnCode::EndOfData.name());
+        assert_eq!("invalid argument", ReturnCode::InvalidArgument.description());
+        assert_eq!("syntax error (-63)", ReturnCode::SyntaxError.to_string());
+    }
+}
@@ -0,0 +1,614 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use serde_json::Value;
+use builtin::column_create::ColumnCreate;
+use builtin::command_query::CommandQuery;
+use builtin::table_create::TableCreate;
+use client::GroongaClient;
+use error::Error;
+use groonga_table::GroongaTable;
+
+const TABLE_ARGUMENTS: &'static [&'static str] = &["flags",
+                                                   "key_type",
+                                                   "value_type",
+                                                   "default_tokenizer",
+                                                   "normalizer",
+                                                   "token_filters"];
+const COLUMN_ARGUMENTS: &'static [&'static str] = &["flags", "type", "source"];
+
+/// Column in the live schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LiveColumn {
+    name: String,
+    arguments: BTreeMap<String, String>,
+}
+
+impl LiveColumn {
+    /// Return column name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return an argument of `column_create` which creates this column.
+    pub fn argument(&self, key: &str) -> Option<&str> {
+        self.arguments.get(key).map(|value| value.as_str())
+    }
+}
+
+/// Table in the live schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LiveTable {
+    name: String,
+    arguments: BTreeMap<String, String>,
+    columns: BTreeMap<String, LiveColumn>,
+}
+
+impl LiveTable {
+    /// Return table name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return an argument of `table_create` which creates this table.
+    pub fn argument(&self, key: &str) -> Option<&str> {
+        self.arguments.get(key).map(|value| value.as_str())
+    }
+
+    /// Return column which has the specified name.
+    pub fn column(&self, name: &str) -> Option<&LiveColumn> {
+        self.columns.get(name)
+    }
+
+    /// Return columns in name order.
+    pub fn columns(&self) -> Vec<&LiveColumn> {
+        self.columns.values().collect()
+    }
+}
+
+/// Tables and columns which are reported by `schema` command.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct LiveSchema {
+    tables: BTreeMap<String, LiveTable>,
+}
+
+impl LiveSchema {
+    #[doc(hidden)]
+    pub fn from_body(body: &Value) -> Result<LiveSchema, Error> {
+        let tables = try!(body.get("tables")
+            .and_then(|tables| tables.as_object())
+            .ok_or_else(|| Error::Json("schema does not have tables".to_owned())));
+        let mut schema = LiveSchema::default();
+        for (name, table) in tables {
+            let mut columns = BTreeMap::new();
+            let live_columns = table.get("columns").and_then(|columns| columns.as_object());
+            if let Some(live_columns) = live_columns {
+                for (column_name, column) in live_columns {
+                    let full_name = format!("{}.{}", name, column_name);
+                    columns.insert(column_name.clone(),
+                                   LiveColumn {
+                                       name: column_name.clone(),
+                                       arguments: try!(parse_arguments(column, &full_name)),
+                                   });
+                }
+            }
+            schema.tables.insert(name.clone(),
+                                 LiveTable {
+                                     name: name.clone(),
+                                     arguments: try!(parse_arguments(table, name)),
+                                     columns: columns,
+                                 });
+        }
+        Ok(schema)
+    }
+
+    /// Return table which has the specified name.
+    pub fn table(&self, name: &str) -> Option<&LiveTable> {
+        self.tables.get(name)
+    }
+
+    /// Return tables in name order.
+    pub fn tables(&self) -> Vec<&LiveTable> {
+        self.tables.values().collect()
+    }
+}
+
+// Each object has `"command": {"name": ..., "arguments": {...}}` which recreates itself.
+fn parse_arguments(object: &Value, name: &str) -> Result<BTreeMap<String, String>, Error> {
+    let arguments = try!(object.get("command")
+        .and_then(|command| command.get("arguments"))
+        .and_then(|arguments| arguments.as_object())
+        .ok_or_else(|| Error::Json(format!("schema of `{}` does not have command", name))));
+    arguments.iter()
+        .map(|(key, value)| {
+            value.as_str()
+                .map(|value| (key.clone(), value.to_owned()))
+                .ok_or_else(|| {
+                    Error::Json(format!("argument `{}` of `{}` is not string", key, name))
+                })
+        })
+        .collect()
+}
+
+/// Difference between the desired schema and the live schema.
+///
+/// Names of columns are full names such as `Sites.title`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SchemaChange {
+    /// Table does not exist.
+    MissingTable(String),
+    /// Scalar or vector column does not exist.
+    MissingColumn(String),
+    /// Index column does not exist.
+    MissingIndex(String),
+    /// Table exists with different arguments.
+    ChangedTable(String, Vec<String>),
+    /// Scalar or vector column exists with different arguments.
+    ChangedColumn(String, Vec<String>),
+    /// Index column exists with different arguments.
+    ChangedIndex(String, Vec<String>),
+}
+
+impl SchemaChange {
+    /// Return whether the change cannot be migrated without losing data.
+    ///
+    /// Changed indexes are migrated by recreating them, but changed tables
+    /// and columns must be migrated by hand.
+    pub fn is_conflict(&self) -> bool {
+        match *self {
+            SchemaChange::ChangedTable(..) |
+            SchemaChange::ChangedColumn(..) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SchemaChange::MissingTable(ref name) => write!(f, "missing table {}", name),
+            SchemaChange::MissingColumn(ref name) => write!(f, "missing column {}", name),
+            SchemaChange::MissingIndex(ref name) => write!(f, "missing index {}", name),
+            SchemaChange::ChangedTable(ref name, ref differences) => {
+                write!(f, "changed table {} ({})", name, differences.join(", "))
+            }
+            SchemaChange::ChangedColumn(ref name, ref differences) => {
+                write!(f, "changed column {} ({})", name, differences.join(", "))
+            }
+            SchemaChange::ChangedIndex(ref name, ref differences) => {
+                write!(f, "changed index {} ({})", name, differences.join(", "))
+            }
+        }
+    }
+}
+
+/// Ordered commands which migrate the live schema to the desired one.
+///
+/// Commands are ordered as removing changed indexes, creating tables in
+/// the order of references, creating columns and creating indexes.
+#[derive(Clone, Debug)]
+pub struct Migration<'a> {
+    changes: Vec<SchemaChange>,
+    commands: Vec<CommandQuery<'a>>,
+}
+
+impl<'a> Migration<'a> {
+    /// Return differences between the desired schema and the live schema.
+    pub fn changes(&self) -> &[SchemaChange] {
+        &self.changes
+    }
+
+    /// Return changes which cannot be migrated automatically.
+    pub fn conflicts(&self) -> Vec<&SchemaChange> {
+        self.changes.iter().filter(|change| change.is_conflict()).collect()
+    }
+
+    /// Return commands to execute.
+    pub fn commands(&self) -> &[CommandQuery<'a>] {
+        &self.commands
+    }
+
+    /// Return whether the live schema is already up to date.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Write changes and commands without executing them.
+    pub fn dry_run(&self, out: &mut Write) -> Result<(), Error> {
+        for change in &self.changes {
+            try!(writeln!(out, "# {}", change));
+        }
+        for command in &self.commands {
+            try!(writeln!(out, "{}", command.encode()));
+        }
+        Ok(())
+    }
+
+    /// Execute commands in order.
+    ///
+    /// This method returns `Error::InvalidArgument` without executing any
+    /// command if the migration has conflicts, and stops at the first
+    /// failed command.
+    pub fn execute<C: GroongaClient>(&self, client: &mut C) -> Result<(), Error> {
+        let conflicts = self.conflicts();
+        if !conflicts.is_empty() {
+            let conflicts = conflicts.iter().map(|change| change.to_string()).collect::<Vec<_>>();
+            return Err(Error::InvalidArgument(format!("schema has conflicts: {}",
+                                                      conflicts.join("; "))));
+        }
+        for command in &self.commands {
+            try!(try!(client.execute(command, None)).check_status());
+        }
+        Ok(())
+    }
+}
+
+/// Declarative schema which consists of tables, columns and indexes.
+///
+/// # Examples
+///
+/// ```
+/// extern crate ruroonga_client;
+/// extern crate serde_json;
+///
+/// use ruroonga_client::{LiveSchema, Schema};
+/// use ruroonga_client::builtin::column_create::{ColumnCreate, ColumnType};
+/// use ruroonga_client::builtin::table_create::{TableCreate, TableType};
+///
+/// # fn main() {
+/// let schema = Schema::new()
+///     .table(TableCreate::new("Sites", TableType::HashKey).key_type("ShortText"))
+///     .column(ColumnCreate::new("Sites", "title", ColumnType::Scalar, "ShortText"));
+/// let live = LiveSchema::from_body(&serde_json::from_str(r#"{"tables":{}}"#).unwrap())
+///     .unwrap();
+/// let migration = schema.diff(&live).unwrap();
+/// assert_eq!(2, migration.changes().len());
+/// assert_eq!("/d/table_create?name=Sites&flags=TABLE_HASH_KEY&key_type=ShortText",
+///            migration.commands()[0].encode());
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Schema<'a> {
+    tables: Vec<TableCreate<'a>>,
+    columns: Vec<ColumnCreate<'a>>,
+}
+
+impl<'a> Schema<'a> {
+    /// Create empty schema.
+    pub fn new() -> Schema<'a> {
+        Schema::default()
+    }
+
+    /// Append a table.
+    pub fn table(mut self, table: TableCreate<'a>) -> Schema<'a> {
+        self.tables.push(table);
+        self
+    }
+
+    /// Append a column or an index.
+    pub fn column(mut self, column: ColumnCreate<'a>) -> Schema<'a> {
+        self.columns.push(column);
+        self
+    }
+
+    /// Append a table which is mapped to `T`, its columns and indexes.
+    pub fn declare<T: GroongaTable>(self) -> Schema<'a> {
+        T::declare(self)
+    }
+
+    /// Fetch the live schema via `schema` command and compute migration.
+    pub fn plan<C: GroongaClient>(&self, client: &mut C) -> Result<Migration<'a>, Error> {
+        let live = try!(try!(client.execute(&CommandQuery::new("schema"), None)).schema());
+        self.diff(&live)
+    }
+
+    /// Compute migration from the live schema.
+    ///
+    /// This method returns `Error::InvalidArgument` if a table or a column
+    /// has invalid arguments, a column belongs to an undefined table or
+    /// tables refer to each other.
+    pub fn diff(&self, live: &LiveSchema) -> Result<Migration<'a>, Error> {
+        let mut changes = vec![];
+        let mut missing_tables = vec![];
+        for table in &self.tables {
+            let command = try!(table.clone().build());
+            let name = argument(&command, "name").to_owned();
+            match live.table(&name) {
+                Some(live_table) => {
+                    let differences =
+                        differences(&command, &live_table.arguments, TABLE_ARGUMENTS);
+                    if !differences.is_empty() {
+                        changes.push(SchemaChange::ChangedTable(name, differences));
+                    }
+                }
+                None => {
+                    changes.push(SchemaChange::MissingTable(name));
+                    missing_tables.push(command);
+                }
+            }
+        }
+
+        let mut removed_indexes = vec![];
+        let mut columns = vec![];
+        let mut indexes = vec![];
+        for column in &self.columns {
+            let command = try!(column.clone().build());
+            let table = argument(&command, "table").to_owned();
+            let full_name = format!("{}.{}", table, argument(&command, "name"));
+            let is_index = argument(&command, "flags")
+                .split('|')
+                .any(|flag| flag == "COLUMN_INDEX");
+            let live_table = live.table(&table);
+            let is_missing = missing_tables.iter()
+                .any(|missing| argument(missing, "name") == table);
+            if live_table.is_none() && !is_missing {
+                return Err(Error::InvalidArgument(format!("{}: table {} is not defined",
+                                                          full_name,
+                                                          table)));
+            }
+            let live_column = live_table.and_then(|live_table| {
+                live_table.column(argument(&command, "name"))
+            });
+            match live_column {
+                Some(live_column) => {
+                    let differences =
+                        differences(&command, &live_column.arguments, COLUMN_ARGUMENTS);
+                    if differences.is_empty() {
+                        continue;
+                    }
+                    if is_index {
+                        changes.push(SchemaChange::ChangedIndex(full_name, differences));
+                        let mut remove = CommandQuery::new("column_remove");
+                        remove.push_argument("table", table);
+                        remove.push_argument("name", live_column.name.clone());
+                        removed_indexes.push(remove);
+                        indexes.push(command);
+                    } else {
+                        changes.push(SchemaChange::ChangedColumn(full_name, differences));
+                    }
+                }
+                None if is_index => {
+                    changes.push(SchemaChange::MissingIndex(full_name));
+                    indexes.push(command);
+                }
+                None => {
+                    changes.push(SchemaChange::MissingColumn(full_name));
+                    columns.push(command);
+                }
+            }
+        }
+
+        let mut commands = removed_indexes;
+        commands.extend(try!(order_tables(missing_tables)));
+        commands.extend(columns);
+        commands.extend(indexes);
+        Ok(Migration {
+            changes: changes,
+            commands: commands,
+        })
+    }
+}
+
+fn argument<'b>(command: &'b CommandQuery, key: &str) -> &'b str {
+    command.get_argument(key).unwrap_or("")
+}
+
+// Flags are compared regardless of their order.
+fn differences(desired: &CommandQuery,
+               live: &BTreeMap<String, String>,
+               keys: &[&str])
+               -> Vec<String> {
+    let mut differences = vec![];
+    for key in keys {
+        let desired = argument(desired, key);
+        let live = live.get(*key).map_or("", |value| value.as_str());
+        let is_same = if *key == "flags" {
+            let mut desired_flags = desired.split('|').collect::<Vec<_>>();
+            let mut live_flags = live.split('|').collect::<Vec<_>>();
+            desired_flags.sort();
+            live_flags.sort();
+            desired_flags == live_flags
+        } else {
+            desired == live
+        };
+        if !is_same {
+            differences.push(format!("{}: `{}` -> `{}`", key, live, desired));
+        }
+    }
+    differences
+}
+
+// Create tables which are referred by `key_type` or `value_type` of other tables first.
+fn order_tables<'a>(tables: Vec<CommandQuery<'a>>) -> Result<Vec<CommandQuery<'a>>, Error> {
+    let mut pending = tables;
+    let mut ordered = vec![];
+    while !pending.is_empty() {
+        let names = pending.iter()
+            .map(|table| argument(table, "name").to_owned())
+            .collect::<Vec<_>>();
+        let (ready, rest): (Vec<_>, Vec<_>) = pending.into_iter().partition(|table| {
+            ["key_type", "value_type"]
+                .iter()
+                .all(|key| !names.iter().any(|name| name == argument(table, key)))
+        });
+        if ready.is_empty() {
+            return Err(Error::InvalidArgument(format!("tables refer to each other: {}",
+                                                      names.join(", "))));
+        }
+        ordered.extend(ready);
+        pending = rest;
+    }
+    Ok(ordered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+    use builtin::column_create::{ColumnCreate, ColumnType};
+    use builtin::table_create::{TableCreate, TableType};
+    use result_parser::ResultParser;
+
+    const LIVE_SCHEMA: &'static str = r#"{"tables": {
+        "Sites": {
+            "name": "Sites",
+            "command": {"name": "table_create",
+                        "arguments": {"name": "Sites", "flags": "TABLE_HASH_KEY",
+                                      "key_type": "ShortText"}},
+            "columns": {
+                "title": {
+                    "name": "title",
+                    "command": {"name": "column_create",
+                                "arguments": {"table": "Sites", "name": "title",
+                                              "flags": "COLUMN_SCALAR", "type": "ShortText"}}
+                }
+            }
+        },
+        "Terms": {
+            "name": "Terms",
+            "command": {"name": "table_create",
+                        "arguments": {"name": "Terms", "flags": "TABLE_PAT_KEY",
+                                      "key_type": "ShortText",
+                                      "default_tokenizer": "TokenBigram"}},
+            "columns": {
+                "sites_title": {
+                    "name": "sites_title",
+                    "command": {"name": "column_create",
+                                "arguments": {"table": "Terms", "name": "sites_title",
+                                              "flags": "COLUMN_INDEX", "type": "Sites",
+                                              "source": "title"}}
+                }
+            }
+        }
+    }}"#;
+
+    fn live_schema() -> LiveSchema {
+        LiveSchema::from_body(&serde_json::from_str(LIVE_SCHEMA).unwrap()).unwrap()
+    }
+
+    fn schema<'a>() -> Schema<'a> {
+        Schema::new()
+            .table(TableCreate::new("Sites", TableType::HashKey).key_type("ShortText"))
+            .table(TableCreate::new("Terms", TableType::PatKey)
+                .key_type("ShortText")
+                .default_tokenizer("TokenBigram"))
+            .column(ColumnCreate::new("Sites", "title", ColumnType::Scalar, "ShortText"))
+            .column(ColumnCreate::new("Terms", "sites_title", ColumnType::Index, "Sites")
+                .source("title"))
+    }
+
+    struct MockClient {
+        commands: Vec<String>,
+    }
+
+    impl GroongaClient for MockClient {
+        fn execute<'a>(&mut self,
+                       command: &CommandQuery<'a>,
+                       _: Option<&str>)
+                       -> Result<ResultParser, Error> {
+            self.commands.push(command.encode());
+            if command.get_command() == "schema" {
+                ResultParser::parse(&format!("[[0,0.0,0.0],{}]", LIVE_SCHEMA))
+            } else {
+                ResultParser::parse("[[0,0.0,0.0],true]")
+            }
+        }
+    }
+
+    #[test]
+    fn parse_live_schema() {
+        let live = live_schema();
+        assert_eq!(2, live.tables().len());
+        let terms = live.table("Terms").unwrap();
+        assert_eq!(Some("TokenBigram"), terms.argument("default_tokenizer"));
+        assert_eq!(Some("title"), terms.column("sites_title").unwrap().argument("source"));
+    }
+
+    #[test]
+    fn up_to_date_schema() {
+        let migration = schema().diff(&live_schema()).unwrap();
+        assert!(migration.is_empty());
+        assert!(migration.commands().is_empty());
+    }
+
+    #[test]
+    fn migrate_missing_tables_in_reference_order() {
+        let migration = Schema::new()
+            .table(TableCreate::new("Comments", TableType::HashKey).key_type("Users"))
+            .table(TableCreate::new("Users", TableType::HashKey).key_type("ShortText"))
+            .column(ColumnCreate::new("Users", "name", ColumnType::Scalar, "ShortText"))
+            .column(ColumnCreate::new("Sites", "body", ColumnType::Scalar, "Text"))
+            .diff(&live_schema())
+            .unwrap();
+        assert_eq!(vec![SchemaChange::MissingTable("Comments".to_owned()),
+                        SchemaChange::MissingTable("Users".to_owned()),
+                        SchemaChange::MissingColumn("Users.name".to_owned()),
+                        SchemaChange::MissingColumn("Sites.body".to_owned())],
+                   migration.changes());
+        let commands = migration.commands().iter().map(|command| command.make_query());
+        assert_eq!(vec!["name=Users&flags=TABLE_HASH_KEY&key_type=ShortText",
+                        "name=Comments&flags=TABLE_HASH_KEY&key_type=Users",
+                        "table=Users&name=name&flags=COLUMN_SCALAR&type=ShortText",
+                        "table=Sites&name=body&flags=COLUMN_SCALAR&type=Text"],
+                   commands.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn recreate_changed_index() {
+        let migration = schema()
+            .column(ColumnCreate::new("Sites", "body", ColumnType::Scalar, "Text"))
+            .column(ColumnCreate::new("Terms", "sites_title", ColumnType::Index, "Sites")
+                .with_position(true)
+                .source("title"))
+            .diff(&live_schema())
+            .unwrap();
+        assert!(migration.conflicts().is_empty());
+        let mut out = vec![];
+        migration.dry_run(&mut out).unwrap();
+        assert_eq!("# missing column Sites.body\n\
+                    # changed index Terms.sites_title \
+                    (flags: `COLUMN_INDEX` -> `COLUMN_INDEX|WITH_POSITION`)\n\
+                    /d/column_remove?table=Terms&name=sites_title\n\
+                    /d/column_create?table=Sites&name=body&flags=COLUMN_SCALAR&type=Text\n\
+                    /d/column_create?table=Terms&name=sites_title&\
+                    flags=COLUMN_INDEX%7CWITH_POSITION&type=Sites&source=title\n",
+                   String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn reject_undefined_table_and_circular_reference() {
+        let undefined = Schema::new()
+            .column(ColumnCreate::new("Users", "name", ColumnType::Scalar, "ShortText"));
+        let circular = Schema::new()
+            .table(TableCreate::new("Users", TableType::HashKey).key_type("Comments"))
+            .table(TableCreate::new("Comments", TableType::HashKey).key_type("Users"));
+        for schema in vec![undefined, circular] {
+            match schema.diff(&live_schema()) {
+                Err(Error::InvalidArgument(_)) => (),
+                _ => panic!("invalid schema should be rejected"),
+            }
+        }
+    }
+
+    #[test]
+    fn execute_migration() {
+        let mut client = MockClient { commands: vec![] };
+        let migration = schema()
+            .column(ColumnCreate::new("Sites", "body", ColumnType::Scalar, "Text"))
+            .plan(&mut client)
+            .unwrap();
+        migration.execute(&mut client).unwrap();
+        assert_eq!(vec!["/d/schema?",
+                        "/d/column_create?table=Sites&name=body&flags=COLUMN_SCALAR&type=Text"],
+                   client.commands);
+
+        let mut client = MockClient { commands: vec![] };
+        let migration = Schema::new()
+            .table(TableCreate::new("Sites", TableType::PatKey).key_type("ShortText"))
+            .plan(&mut client)
+            .unwrap();
+        assert_eq!(1, migration.conflicts().len());
+        assert!(migration.execute(&mut client).is_err());
+        assert_eq!(vec!["/d/schema?"], client.commands);
+    }
+}
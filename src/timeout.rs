@@ -0,0 +1,162 @@
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Timeout settings which are shared with HTTP and GQTP transports.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timeouts {
+    pub connect: Option<Duration>,
+    pub read: Option<Duration>,
+    pub write: Option<Duration>,
+    pub deadline: Option<Duration>,
+}
+
+impl Timeouts {
+    // Return remaining time until per-request deadline.
+    pub fn remaining(&self, started: Instant) -> io::Result<Option<Duration>> {
+        match self.deadline {
+            Some(deadline) => {
+                let elapsed = started.elapsed();
+                if elapsed >= deadline {
+                    Err(timed_out())
+                } else {
+                    Ok(Some(deadline - elapsed))
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn connect_timeout(&self, started: Instant) -> io::Result<Option<Duration>> {
+        Ok(shorter(self.connect, try!(self.remaining(started))))
+    }
+
+    pub fn read_timeout(&self, started: Instant) -> io::Result<Option<Duration>> {
+        Ok(shorter(self.read, try!(self.remaining(started))))
+    }
+
+    pub fn write_timeout(&self, started: Instant) -> io::Result<Option<Duration>> {
+        Ok(shorter(self.write, try!(self.remaining(started))))
+    }
+
+    // Open TcpStream with connect timeout and set read/write timeouts on it.
+    pub fn connect<A>(&self, addr: A, started: Instant) -> io::Result<TcpStream>
+        where A: ToSocketAddrs
+    {
+        let stream = match try!(self.connect_timeout(started)) {
+            Some(timeout) => {
+                let mut last_err = None;
+                let mut connected = None;
+                for addr in try!(addr.to_socket_addrs()) {
+                    match TcpStream::connect_timeout(&addr, timeout) {
+                        Ok(stream) => {
+                            connected = Some(stream);
+                            break;
+                        }
+                        Err(err) => last_err = Some(normalize(err)),
+                    }
+                }
+                match connected {
+                    Some(stream) => stream,
+                    None => {
+                        return Err(last_err.unwrap_or_else(|| {
+                            io::Error::new(io::ErrorKind::InvalidInput,
+                                           "could not resolve to any addresses")
+                        }))
+                    }
+                }
+            }
+            None => try!(TcpStream::connect(addr)),
+        };
+        try!(stream.set_read_timeout(try!(self.read_timeout(started))));
+        try!(stream.set_write_timeout(try!(self.write_timeout(started))));
+        Ok(stream)
+    }
+}
+
+// Start time of the current request, which is shared with connections
+// that are opened or reused for the request.
+#[derive(Clone, Debug, Default)]
+pub struct RequestClock(Arc<Mutex<Option<Instant>>>);
+
+impl RequestClock {
+    // Start a new request and return its start time.
+    pub fn start(&self) -> Instant {
+        let started = Instant::now();
+        *self.0.lock().unwrap_or_else(|err| err.into_inner()) = Some(started);
+        started
+    }
+
+    // Return start time of the current request, or now if none is started.
+    pub fn started(&self) -> Instant {
+        self.0
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .unwrap_or_else(Instant::now)
+    }
+}
+
+fn shorter(a: Option<Duration>, b: Option<Duration>) -> Option<Duration> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if a < b { a } else { b }),
+        (Some(a), None) => Some(a),
+        (None, b) => b,
+    }
+}
+
+pub fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "operation timed out")
+}
+
+// Read timeout is reported as `WouldBlock` on some platforms.
+// Unify it into `TimedOut`.
+pub fn normalize(err: io::Error) -> io::Error {
+    match err.kind() {
+        io::ErrorKind::WouldBlock |
+        io::ErrorKind::TimedOut => timed_out(),
+        _ => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_timeouts() {
+        let timeouts = Timeouts::default();
+        let started = Instant::now();
+        assert_eq!(None, timeouts.remaining(started).unwrap());
+        assert_eq!(None, timeouts.read_timeout(started).unwrap());
+    }
+
+    #[test]
+    fn shorter_timeout_is_used() {
+        let timeouts = Timeouts {
+            read: Some(Duration::from_secs(1)),
+            deadline: Some(Duration::from_secs(60)),
+            ..Timeouts::default()
+        };
+        let started = Instant::now();
+        assert_eq!(Some(Duration::from_secs(1)),
+                   timeouts.read_timeout(started).unwrap());
+        assert!(timeouts.connect_timeout(started).unwrap().unwrap() <= Duration::from_secs(60));
+    }
+
+    #[test]
+    fn expired_deadline() {
+        let timeouts = Timeouts {
+            deadline: Some(Duration::from_millis(0)),
+            ..Timeouts::default()
+        };
+        let err = timeouts.remaining(Instant::now()).unwrap_err();
+        assert_eq!(io::ErrorKind::TimedOut, err.kind());
+    }
+
+    #[test]
+    fn normalize_would_block() {
+        let err = normalize(io::Error::new(io::ErrorKind::WouldBlock, "would block"));
+        assert_eq!(io::ErrorKind::TimedOut, err.kind());
+    }
+}
@@ -2,6 +2,7 @@ use std::borrow::Cow;
 
 #[derive(Clone, Debug)]
 pub struct URIBase<'a> {
+    scheme: Cow<'a, str>,
     base_uri: Cow<'a, str>,
     port: u16,
 }
@@ -9,6 +10,7 @@ pub struct URIBase<'a> {
 impl<'a> Default for URIBase<'a> {
     fn default() -> URIBase<'a> {
         URIBase {
+            scheme: "http".into(),
             base_uri: "localhost".into(),
             port: 10041,
         }
@@ -21,6 +23,8 @@ impl<'a> URIBase<'a> {
     ///
     /// Default values are:
     ///
+    ///   scheme: "http"
+    ///
     ///   base_uri: "localhost"
     ///
     ///   port: 10041
@@ -29,6 +33,16 @@ impl<'a> URIBase<'a> {
         URIBase::default()
     }
 
+    /// Set scheme to replace default value with specified value.
+    ///
+    /// Use `"https"` to talk with Groonga behind TLS-terminating server.
+    pub fn scheme<T>(mut self, scheme: T) -> URIBase<'a>
+        where T: Into<Cow<'a, str>>
+    {
+        self.scheme = scheme.into();
+        self
+    }
+
     /// Set base to replace default value with specified value.
     pub fn base_uri<T>(mut self, base_uri: T) -> URIBase<'a>
         where T: Into<Cow<'a, str>>
@@ -45,7 +59,10 @@ impl<'a> URIBase<'a> {
 
     /// Build and get base uri.
     pub fn build(self) -> String {
-        format!("http://{}:{}", self.base_uri.into_owned(), self.port)
+        format!("{}://{}:{}",
+                self.scheme.into_owned(),
+                self.base_uri.into_owned(),
+                self.port)
     }
 }
 
@@ -86,4 +103,10 @@ mod tests {
         assert_eq!("http://127.0.1.1:10043", uri_base)
     }
 
+    #[test]
+    fn build_only_scheme() {
+        let uri_base = URIBase::new().scheme("https").build();
+        assert_eq!("https://localhost:10041", uri_base);
+    }
+
 }